@@ -0,0 +1,110 @@
+use crate::audio::AudioBackend;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+// The most samples which may wait to be played, in seconds, older ones are dropped so the
+// sound never lags far behind the screen
+const MAX_LATENCY_SECONDS: f32 = 0.1;
+
+// How much the last sample fades by each sample while the device is waiting for more, so
+// running out of samples does not click
+const UNDERRUN_FADE: f32 = 0.99;
+
+// The reasons the speakers could not be opened
+#[derive(Debug)]
+pub enum AudioError {
+    // There is no output device
+    NoDevice,
+    // The output device would not say how it plays sound
+    Config(cpal::DefaultStreamConfigError),
+    // The output device would not open a stream
+    Build(cpal::BuildStreamError),
+    // The output device would not start the stream
+    Play(cpal::PlayStreamError),
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AudioError::NoDevice => write!(f, "no audio output device"),
+            AudioError::Config(error) => write!(f, "could not configure audio: {error}"),
+            AudioError::Build(error) => write!(f, "could not open audio: {error}"),
+            AudioError::Play(error) => write!(f, "could not start audio: {error}"),
+        }
+    }
+}
+
+impl Error for AudioError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AudioError::NoDevice => None,
+            AudioError::Config(error) => Some(error),
+            AudioError::Build(error) => Some(error),
+            AudioError::Play(error) => Some(error),
+        }
+    }
+}
+
+// Plays the samples through the default output device as they are made
+pub struct LiveBackend {
+    // The number of samples the device plays each second
+    sample_rate: u32,
+    // The samples waiting to be played, shared with the device's callback
+    queue: Arc<Mutex<VecDeque<f32>>>,
+    // The stream playing the samples, which stops when it is dropped
+    _stream: cpal::Stream,
+}
+
+impl LiveBackend {
+    /// Returns a LiveBackend playing through the default output device, at its own sample rate
+    pub fn new() -> Result<LiveBackend, AudioError> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or(AudioError::NoDevice)?;
+        let config = device
+            .default_output_config()
+            .map_err(AudioError::Config)?
+            .config();
+        let channels = config.channels as usize;
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+        let stream_queue = Arc::clone(&queue);
+        let mut last = 0.0;
+        let stream = device
+            .build_output_stream(
+                &config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    let mut queue = stream_queue.lock().unwrap();
+                    for frame in data.chunks_mut(channels) {
+                        last = queue.pop_front().unwrap_or(last * UNDERRUN_FADE);
+                        frame.fill(last);
+                    }
+                },
+                |error| eprintln!("audio: {error}"),
+                None,
+            )
+            .map_err(AudioError::Build)?;
+        stream.play().map_err(AudioError::Play)?;
+        Ok(LiveBackend {
+            sample_rate: config.sample_rate.0,
+            queue,
+            _stream: stream,
+        })
+    }
+}
+
+impl AudioBackend for LiveBackend {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn play(&mut self, samples: &[f32]) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.extend(samples);
+        let max = (self.sample_rate as f32 * MAX_LATENCY_SECONDS) as usize;
+        let excess = queue.len().saturating_sub(max);
+        queue.drain(..excess);
+    }
+}
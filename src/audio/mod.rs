@@ -0,0 +1,459 @@
+#[cfg(feature = "audio")]
+mod live;
+mod wav;
+
+#[cfg(feature = "audio")]
+pub use crate::audio::live::{AudioError, LiveBackend};
+pub use crate::audio::wav::WavBackend;
+
+use crate::cpu::{Cpu, DigitizedSound, MegaChip, AUDIO_PATTERN_BYTES, DEFAULT_PITCH, TIMER_HZ};
+
+// The pitch of the buzzer, in Hz
+pub const BEEP_HZ: f32 = 440.0;
+
+// The number of one-bit samples in an XO-CHIP audio pattern
+const PATTERN_BITS: usize = AUDIO_PATTERN_BYTES * 8;
+
+// The rate the XO-CHIP audio pattern is played at when the pitch is DEFAULT_PITCH, in bits per
+// second
+pub const PATTERN_HZ: f32 = 4000.0;
+
+// How loud the buzzer is, as a fraction of full scale
+const VOLUME: f32 = 0.25;
+
+// How long the buzzer takes to fade in and out, so it starts and stops without a click
+const RAMP_SECONDS: f32 = 0.002;
+
+// Somewhere the samples of the buzzer are sent to, e.g. the speakers or a file
+pub trait AudioBackend {
+    /// Returns the number of samples played each second
+    fn sample_rate(&self) -> u32;
+
+    /// Plays the samples, which are mono and between -1 and 1, after the ones played before them
+    ///	samples - The samples to play
+    fn play(&mut self, samples: &[f32]);
+}
+
+/// Returns the rate an XO-CHIP audio pattern is played at, in bits per second. Every 48 steps of
+/// pitch doubles the rate
+///	pitch - The pitch set by Fx3A
+pub fn pattern_rate(pitch: u8) -> f32 {
+    PATTERN_HZ * 2f32.powf((pitch as f32 - DEFAULT_PITCH as f32) / 48.0)
+}
+
+// What the buzzer plays while it sounds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tone {
+    // A square wave at BEEP_HZ
+    Beep,
+    // An XO-CHIP audio pattern, looped at the rate its pitch gives
+    Pattern {
+        bits: [u8; AUDIO_PATTERN_BYTES],
+        pitch: u8,
+    },
+}
+
+impl Tone {
+    /// Returns the tone the Cpu's buzzer plays, its audio pattern if a rom has loaded one and a
+    /// beep otherwise
+    ///	cpu - The Cpu whose buzzer is played
+    pub fn of(cpu: &Cpu) -> Tone {
+        match cpu.audio_pattern() {
+            Some(&bits) => Tone::Pattern {
+                bits,
+                pitch: cpu.pitch(),
+            },
+            None => Tone::Beep,
+        }
+    }
+}
+
+/// Returns the average of the bits of the pattern, as 1 for a set bit and -1 for a clear one,
+/// between two positions measured in bits. Averaging over the whole sample rather than picking
+/// the nearest bit keeps high pitches from aliasing
+///	bits - The pattern, whose first bit is the top bit of its first byte
+///	start - The position the sample starts at, from 0 to the number of bits in the pattern
+///	width - The number of bits the sample covers
+fn pattern_average(bits: &[u8; AUDIO_PATTERN_BYTES], start: f32, width: f32) -> f32 {
+    let end = start + width;
+    let mut position = start;
+    let mut sum = 0.0;
+    while position < end {
+        let bit = position as usize % PATTERN_BITS;
+        let next = (position.floor() + 1.0).min(end);
+        let set = bits[bit / 8] & (0x80 >> (bit % 8)) != 0;
+        sum += if set { 1.0 } else { -1.0 } * (next - position);
+        position = next;
+    }
+    sum / width
+}
+
+// Makes the square wave of the buzzer, or resamples the XO-CHIP audio pattern. The wave fades in
+// and out over a few milliseconds and always starts on the same edge, so every beep sounds the
+// same and no beep clicks
+#[derive(Clone, Debug)]
+pub struct Beeper {
+    // The number of samples made each second
+    sample_rate: u32,
+    // How far through the current period of the wave or loop of the pattern it is, from 0 to 1
+    phase: f32,
+    // How loud the wave is as it fades in and out, from 0 to 1
+    level: f32,
+}
+
+impl Beeper {
+    /// Returns a new silent Beeper
+    ///	sample_rate - The number of samples made each second
+    pub fn new(sample_rate: u32) -> Beeper {
+        Beeper {
+            sample_rate,
+            phase: 0.0,
+            level: 0.0,
+        }
+    }
+
+    /// Fills out with the next samples of the tone, fading it in while the buzzer sounds and out
+    /// once it stops
+    ///	out - The samples to fill
+    ///	sounding - Whether the buzzer is sounding
+    ///	tone - What the buzzer plays
+    pub fn fill(&mut self, out: &mut [f32], sounding: bool, tone: Tone) {
+        let step = match tone {
+            Tone::Beep => BEEP_HZ,
+            Tone::Pattern { pitch, .. } => pattern_rate(pitch) / PATTERN_BITS as f32,
+        } / self.sample_rate as f32;
+        let ramp = 1.0 / (RAMP_SECONDS * self.sample_rate as f32);
+        for sample in out {
+            if !sounding && self.level == 0.0 {
+                self.phase = 0.0;
+                *sample = 0.0;
+                continue;
+            }
+            self.level = if sounding {
+                (self.level + ramp).min(1.0)
+            } else {
+                (self.level - ramp).max(0.0)
+            };
+            let edge = match &tone {
+                Tone::Beep if self.phase < 0.5 => 1.0,
+                Tone::Beep => -1.0,
+                Tone::Pattern { bits, .. } => pattern_average(
+                    bits,
+                    self.phase * PATTERN_BITS as f32,
+                    step * PATTERN_BITS as f32,
+                ),
+            };
+            *sample = edge * self.level * VOLUME;
+            self.phase = (self.phase + step).fract();
+        }
+    }
+}
+
+// Plays the MegaChip digitized sound out of the Cpu's memory, resampled to the output rate and
+// mixed over the buzzer
+#[derive(Clone, Debug)]
+pub struct SamplePlayer {
+    // The number of samples made each second
+    sample_rate: u32,
+    // The number of the start being played, as counted by the MegaChip, and how far through the
+    // sound it is, in samples of the sound
+    playing: Option<(u32, f32)>,
+}
+
+impl SamplePlayer {
+    /// Returns a new SamplePlayer with no sound playing
+    ///	sample_rate - The number of samples made each second
+    pub fn new(sample_rate: u32) -> SamplePlayer {
+        SamplePlayer {
+            sample_rate,
+            playing: None,
+        }
+    }
+
+    /// Adds the next samples of the digitized sound to out. A sound started since the last call
+    /// plays from its beginning, even if it is the same sound. A sound which does not loop is
+    /// silent once it has finished, until 060n starts one again
+    ///	out - The samples to add to
+    ///	sound - The digitized sound the Cpu is playing, if any
+    ///	started - The number of sounds the Cpu has started, as returned by sounds_started
+    ///	memory - The Cpu's memory, which holds the samples of the sound
+    pub fn mix(
+        &mut self,
+        out: &mut [f32],
+        sound: Option<DigitizedSound>,
+        started: u32,
+        memory: &[u8],
+    ) {
+        let Some(sound) = sound.filter(|sound| sound.len != 0) else {
+            self.playing = None;
+            return;
+        };
+        let position = match self.playing {
+            Some((playing, position)) if playing == started => position,
+            _ => 0.0,
+        };
+        let step = sound.sample_rate as f32 / self.sample_rate as f32;
+        let mut position = position;
+        for sample in out {
+            if position >= sound.len as f32 {
+                if !sound.looping {
+                    break;
+                }
+                position %= sound.len as f32;
+            }
+            let byte = memory[sound.start + position as usize];
+            *sample += (byte as f32 - 128.0) / 128.0 * VOLUME;
+            position += step;
+        }
+        self.playing = Some((started, position));
+    }
+}
+
+// Plays the buzzer of a Cpu through a backend, one frame at a time
+pub struct Audio<B: AudioBackend> {
+    // Where the samples are sent
+    backend: B,
+    // Makes the samples
+    beeper: Beeper,
+    // Mixes in the MegaChip digitized sound
+    sampler: SamplePlayer,
+    // The samples of the frame being made, kept to save allocating them every frame
+    buffer: Vec<f32>,
+    // The part of a sample left over from the frames so far, when the sample rate is not a
+    // multiple of the frame rate
+    leftover: f32,
+}
+
+impl<B: AudioBackend> Audio<B> {
+    /// Returns new Audio playing through the backend
+    ///	backend - Where the samples should be sent
+    pub fn new(backend: B) -> Audio<B> {
+        Audio {
+            beeper: Beeper::new(backend.sample_rate()),
+            sampler: SamplePlayer::new(backend.sample_rate()),
+            backend,
+            buffer: Vec::new(),
+            leftover: 0.0,
+        }
+    }
+
+    /// Plays a 60th of a second of the buzzer, sounding if the Cpu's sound timer is running, with
+    /// the Cpu's audio pattern if it has one, mixed with the MegaChip digitized sound if one is
+    /// playing. Call it once per frame, after the frame has run
+    ///	cpu - The Cpu whose buzzer should be played
+    pub fn frame(&mut self, cpu: &Cpu) {
+        let samples = self.backend.sample_rate() as f32 / TIMER_HZ as f32 + self.leftover;
+        self.leftover = samples.fract();
+        self.buffer.resize(samples as usize, 0.0);
+        self.beeper
+            .fill(&mut self.buffer, cpu.timers().is_sounding(), Tone::of(cpu));
+        let mega = cpu.megachip();
+        let sound = mega.and_then(MegaChip::sound);
+        let started = mega.map_or(0, MegaChip::sounds_started);
+        self.sampler
+            .mix(&mut self.buffer, sound, started, cpu.memory());
+        self.backend.play(&self.buffer);
+    }
+
+    /// Returns the backend the samples are sent to
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Returns the backend the samples were sent to, e.g. to save a file once the rom has run
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::Platform;
+
+    #[test]
+    fn beeper_is_silent_until_the_buzzer_sounds() {
+        let mut beeper = Beeper::new(8000);
+        let mut samples = [1.0; 100];
+        beeper.fill(&mut samples, false, Tone::Beep);
+        assert!(samples.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn beeper_fades_in_and_out() {
+        let mut beeper = Beeper::new(8000);
+        let mut samples = [0.0; 200];
+        beeper.fill(&mut samples, true, Tone::Beep);
+        assert!(samples[0] > 0.0 && samples[0] < VOLUME);
+        assert_eq!(samples[100].abs(), VOLUME);
+
+        beeper.fill(&mut samples, false, Tone::Beep);
+        assert!(samples[0].abs() < VOLUME);
+        assert_eq!(samples[199], 0.0);
+    }
+
+    #[test]
+    fn frames_make_a_60th_of_a_second_of_samples() {
+        let mut cpu = Cpu::default();
+        let mut audio = Audio::new(WavBackend::new(44100));
+        audio.frame(&cpu);
+        assert_eq!(audio.backend().samples().len(), 735);
+
+        cpu.load_rom_bytes(&[0x60, 0x02, 0xf0, 0x18]).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        for _ in 0..3 {
+            audio.frame(&cpu);
+            cpu.tick_timers();
+        }
+        let samples = audio.backend().samples();
+        assert_eq!(samples.len(), 4 * 735);
+        assert!(samples[735..3 * 735].iter().any(|&sample| sample != 0));
+        assert_eq!(samples[4 * 735 - 1], 0);
+    }
+
+    #[test]
+    fn pattern_rate_doubles_every_48_steps_of_pitch() {
+        assert_eq!(pattern_rate(DEFAULT_PITCH), PATTERN_HZ);
+        assert_eq!(pattern_rate(DEFAULT_PITCH + 48), 2.0 * PATTERN_HZ);
+        assert_eq!(pattern_rate(DEFAULT_PITCH - 48), PATTERN_HZ / 2.0);
+    }
+
+    #[test]
+    fn pattern_plays_one_bit_per_sample_at_the_pattern_rate() {
+        let mut bits = [0; AUDIO_PATTERN_BYTES];
+        bits[0] = 0b1010_0000;
+        let tone = Tone::Pattern {
+            bits,
+            pitch: DEFAULT_PITCH,
+        };
+        let mut beeper = Beeper::new(PATTERN_HZ as u32);
+        // Fade in past the ramp, then line up with the start of the next loop of the pattern
+        let mut samples = vec![0.0; PATTERN_BITS * 4];
+        beeper.fill(&mut samples, true, tone);
+        let mut samples = [0.0; 4];
+        beeper.fill(&mut samples, true, tone);
+        assert_eq!(samples, [VOLUME, -VOLUME, VOLUME, -VOLUME]);
+    }
+
+    #[test]
+    fn pattern_is_averaged_when_played_faster_than_the_sample_rate() {
+        let tone = Tone::Pattern {
+            bits: [0b1010_1010; AUDIO_PATTERN_BYTES],
+            pitch: DEFAULT_PITCH + 48,
+        };
+        let mut beeper = Beeper::new(PATTERN_HZ as u32);
+        let mut samples = vec![0.0; PATTERN_BITS * 4];
+        beeper.fill(&mut samples, true, tone);
+        assert!(samples[PATTERN_BITS..].iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn sample_player_resamples_and_loops_the_sound() {
+        let memory = [0x80, 0xc0, 0x40, 0x00];
+        let sound = DigitizedSound {
+            start: 1,
+            len: 2,
+            sample_rate: 4000,
+            looping: true,
+        };
+        let mut player = SamplePlayer::new(8000);
+        let mut samples = [0.0; 6];
+        player.mix(&mut samples, Some(sound), 1, &memory);
+        let high = 0.5 * VOLUME;
+        let low = -0.5 * VOLUME;
+        assert_eq!(samples, [high, high, low, low, high, high]);
+
+        let mut samples = [0.0; 2];
+        player.mix(&mut samples, Some(sound), 1, &memory);
+        assert_eq!(samples, [low, low]);
+    }
+
+    #[test]
+    fn sample_player_stops_at_the_end_of_a_sound_which_does_not_loop() {
+        let memory = [0xc0, 0xc0];
+        let sound = DigitizedSound {
+            start: 0,
+            len: 2,
+            sample_rate: 8000,
+            looping: false,
+        };
+        let mut player = SamplePlayer::new(8000);
+        let mut samples = [0.0; 4];
+        player.mix(&mut samples, Some(sound), 1, &memory);
+        assert_eq!(samples, [0.5 * VOLUME, 0.5 * VOLUME, 0.0, 0.0]);
+        player.mix(&mut samples, None, 1, &memory);
+        assert_eq!(samples, [0.5 * VOLUME, 0.5 * VOLUME, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn frames_play_the_megachip_digitized_sound() {
+        let mut cpu = Cpu::with_platform(Platform::MegaChip);
+        let mut audio = Audio::new(WavBackend::new(8000));
+        // Play a 4 sample sound at 8000 Hz once, then stop
+        #[rustfmt::skip]
+        let rom = [
+            0xa2, 0x04, 0x06, 0x01, 0x1f, 0x40, 0x00, 0x00,
+            0x04, 0x00, 0xff, 0xff, 0xff, 0xff,
+        ];
+        cpu.load_rom_bytes(&rom).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        audio.frame(&cpu);
+        let samples = audio.backend().samples();
+        assert!(samples[..4].iter().all(|&sample| sample > 0));
+        assert!(samples[4..].iter().all(|&sample| sample == 0));
+    }
+
+    #[test]
+    fn frames_play_the_same_sound_again_when_it_is_started_again() {
+        let mut cpu = Cpu::with_platform(Platform::MegaChip);
+        let mut audio = Audio::new(WavBackend::new(8000));
+        // Play a 4 sample sound at 8000 Hz once, then play it again a frame later
+        #[rustfmt::skip]
+        let rom = [
+            0xa2, 0x08, 0x06, 0x01, 0x06, 0x01, 0x12, 0x06,
+            0x1f, 0x40, 0x00, 0x00, 0x04, 0x00, 0xff, 0xff,
+            0xff, 0xff,
+        ];
+        cpu.load_rom_bytes(&rom).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        audio.frame(&cpu);
+        cpu.cycle().unwrap();
+        audio.frame(&cpu);
+        let samples = audio.backend().samples();
+        let frame = samples.len() / 2;
+        for played in [&samples[..frame], &samples[frame..]] {
+            assert!(played[..4].iter().all(|&sample| sample > 0));
+            assert!(played[4..].iter().all(|&sample| sample == 0));
+        }
+    }
+
+    #[test]
+    fn frames_play_the_cpus_audio_pattern() {
+        let mut cpu = Cpu::with_platform(Platform::XoChip);
+        let mut audio = Audio::new(WavBackend::new(44100));
+        // Load an all clear pattern at a lower pitch, then sound the buzzer for a frame
+        #[rustfmt::skip]
+        let rom = [
+            0xa2, 0x0c, 0xf0, 0x02, 0x60, 0x20, 0xf0, 0x3a,
+            0x60, 0x01, 0xf0, 0x18, 0x00, 0x00, 0x00, 0x00,
+        ];
+        cpu.load_rom_bytes(&rom).unwrap();
+        for _ in 0..6 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.pitch(), 0x20);
+        assert_eq!(
+            Tone::of(&cpu),
+            Tone::Pattern {
+                bits: [0; AUDIO_PATTERN_BYTES],
+                pitch: 0x20
+            }
+        );
+        audio.frame(&cpu);
+        let samples = audio.backend().samples();
+        assert!(samples[100..].iter().all(|&sample| sample < 0));
+    }
+}
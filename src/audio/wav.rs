@@ -0,0 +1,100 @@
+use crate::audio::AudioBackend;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// The number of bytes in each sample of the WAV file, which holds 16-bit PCM
+const BYTES_PER_SAMPLE: u16 = 2;
+
+// The number of bytes in the header of the WAV file, before the samples
+const HEADER_BYTES: u32 = 44;
+
+// Keeps every sample played, so they can be checked or written to a WAV file once the rom has
+// run. Lets the buzzer be tested without any audio hardware
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WavBackend {
+    // The number of samples played each second
+    sample_rate: u32,
+    // Every sample played, as 16-bit PCM
+    samples: Vec<i16>,
+}
+
+impl WavBackend {
+    /// Returns a new WavBackend with no samples
+    ///	sample_rate - The number of samples played each second
+    pub fn new(sample_rate: u32) -> WavBackend {
+        WavBackend {
+            sample_rate,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Returns every sample played, as 16-bit PCM
+    pub fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+
+    /// Writes the samples played as a mono 16-bit PCM WAV file
+    ///	writer - Where the file should be written
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        let data_bytes = self.samples.len() as u32 * BYTES_PER_SAMPLE as u32;
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(HEADER_BYTES - 8 + data_bytes).to_le_bytes())?;
+        writer.write_all(b"WAVEfmt ")?;
+        // The format chunk: 16 bytes long, PCM, one channel
+        writer.write_all(&16u32.to_le_bytes())?;
+        writer.write_all(&1u16.to_le_bytes())?;
+        writer.write_all(&1u16.to_le_bytes())?;
+        writer.write_all(&self.sample_rate.to_le_bytes())?;
+        writer.write_all(&(self.sample_rate * BYTES_PER_SAMPLE as u32).to_le_bytes())?;
+        writer.write_all(&BYTES_PER_SAMPLE.to_le_bytes())?;
+        writer.write_all(&(BYTES_PER_SAMPLE * 8).to_le_bytes())?;
+        writer.write_all(b"data")?;
+        writer.write_all(&data_bytes.to_le_bytes())?;
+        for sample in &self.samples {
+            writer.write_all(&sample.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Writes the samples played to a WAV file at path, as write does
+    ///	path - The path of the file to write
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+}
+
+impl AudioBackend for WavBackend {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn play(&mut self, samples: &[f32]) {
+        self.samples.extend(
+            samples
+                .iter()
+                .map(|&sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_a_wav_header_before_the_samples() {
+        let mut wav = WavBackend::new(8000);
+        wav.play(&[0.0, 1.0, -1.0]);
+        let mut file = Vec::new();
+        wav.write(&mut file).unwrap();
+        assert_eq!(file.len(), HEADER_BYTES as usize + 6);
+        assert_eq!(&file[..4], b"RIFF");
+        assert_eq!(file[4..8], (HEADER_BYTES - 8 + 6).to_le_bytes());
+        assert_eq!(file[24..28], 8000u32.to_le_bytes());
+        assert_eq!(file[40..44], 6u32.to_le_bytes());
+        assert_eq!(file[44..], [0, 0, 0xff, 0x7f, 0x01, 0x80]);
+    }
+}
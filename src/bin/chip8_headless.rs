@@ -0,0 +1,156 @@
+//! Runs a rom without a display for a fixed number of cycles or frames, then prints the screen.
+//!
+//! chip8_headless [--platform NAME] [--font NAME] [--big-font NAME] [--font-file PATH]
+//!                [--vip-timing] [--cycles N | --frames N] [--ipf N]
+//!                [--format ascii|pbm|hash] [--wav PATH] [--audio-hash] <rom>
+//!
+//! --audio-hash prints a hash of the sound the rom made after the screen, so regression tests can
+//! check XO-CHIP audio patterns without keeping WAV files.
+
+use chip8_emulator::audio::{Audio, WavBackend};
+use chip8_emulator::cli::{self, CpuOptions};
+use chip8_emulator::{dump, CpuError};
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+// The sample rate of the WAV file written by --wav
+const WAV_SAMPLE_RATE: u32 = 44100;
+
+// How long to run the rom for
+enum RunLength {
+    Cycles(u32),
+    Frames(u32),
+}
+
+// How the screen is printed once the rom has run
+enum Format {
+    Ascii,
+    Pbm,
+    Hash,
+}
+
+struct Options {
+    rom: PathBuf,
+    cpu: CpuOptions,
+    length: RunLength,
+    instructions_per_frame: Option<u32>,
+    format: Format,
+    wav: Option<PathBuf>,
+    audio_hash: bool,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}");
+            eprintln!(
+                "usage: chip8_headless {} [--cycles N | --frames N] [--ipf N] \
+                 [--format ascii|pbm|hash] [--wav PATH] [--audio-hash] <rom>",
+                cli::CPU_USAGE
+            );
+            cli::print_cpu_names();
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut cpu = match options.cpu.build(&options.rom) {
+        Ok(cpu) => cpu,
+        Err(message) => {
+            eprintln!("{message}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let ipf = options
+        .instructions_per_frame
+        .unwrap_or(options.cpu.instructions_per_frame());
+    let mut audio = Audio::new(WavBackend::new(WAV_SAMPLE_RATE));
+    let result: Result<(), CpuError> = match options.length {
+        RunLength::Cycles(cycles) => (1..=cycles).try_for_each(|i| {
+            cpu.cycle()?;
+            if i % ipf == 0 {
+                cpu.tick_timers();
+                audio.frame(&cpu);
+            }
+            Ok(())
+        }),
+        RunLength::Frames(frames) => (0..frames).try_for_each(|_| {
+            cpu.run_frame(ipf)?;
+            audio.frame(&cpu);
+            Ok(())
+        }),
+    };
+    if let Err(error) = result {
+        eprintln!("{error}");
+        return ExitCode::FAILURE;
+    }
+    if let Some(path) = &options.wav {
+        if let Err(error) = audio.backend().save(path) {
+            eprintln!("{}: {error}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    match options.format {
+        Format::Ascii => print!("{}", dump::to_ascii(&cpu)),
+        Format::Pbm => print!("{}", dump::to_pbm(&cpu)),
+        Format::Hash => println!("{:016x}", dump::screen_hash(&cpu)),
+    }
+    if options.audio_hash {
+        println!("{:016x}", dump::audio_hash(audio.backend().samples()));
+    }
+    ExitCode::SUCCESS
+}
+
+/// Parses the command line arguments, after the program name, into Options
+///	args - The arguments to parse
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut rom = None;
+    let mut cpu = CpuOptions::default();
+    let mut length = RunLength::Frames(60);
+    let mut instructions_per_frame = None;
+    let mut format = Format::Ascii;
+    let mut wav = None;
+    let mut audio_hash = false;
+
+    while let Some(arg) = args.next() {
+        if cpu.parse_arg(&arg, &mut args)? {
+            continue;
+        }
+        let mut value = |name: &str| cli::value(name, &mut args);
+        match arg.as_str() {
+            "--cycles" => length = RunLength::Cycles(parse_count(&value("--cycles")?)?),
+            "--frames" => length = RunLength::Frames(parse_count(&value("--frames")?)?),
+            "--ipf" => instructions_per_frame = Some(parse_count(&value("--ipf")?)?.max(1)),
+            "--audio-hash" => audio_hash = true,
+            "--wav" => wav = Some(PathBuf::from(value("--wav")?)),
+            "--format" => {
+                format = match value("--format")?.as_str() {
+                    "ascii" => Format::Ascii,
+                    "pbm" => Format::Pbm,
+                    "hash" => Format::Hash,
+                    other => return Err(format!("unknown format {other}")),
+                }
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => rom = Some(PathBuf::from(arg)),
+        }
+    }
+
+    Ok(Options {
+        rom: rom.ok_or("no rom given")?,
+        cpu,
+        length,
+        instructions_per_frame,
+        format,
+        wav,
+        audio_hash,
+    })
+}
+
+/// Parses a count passed to an option
+///	value - The text of the count
+fn parse_count(value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("{value} is not a count"))
+}
@@ -0,0 +1,230 @@
+//! Runs a rom in the terminal, for playing over SSH without a display server.
+//!
+//! chip8_terminal [--platform NAME] [--font NAME] [--big-font NAME] [--font-file PATH]
+//!                [--vip-timing] [--palette NAME] [--colours HEX,...] [--palette-file PATH]
+//!                [--braille] <rom>
+//!
+//! The keypad is on the left of the keyboard as in the window frontend, P pauses and Esc or
+//! Ctrl+C quits. Most terminals only report key presses, so a key is held until it has not been
+//! pressed or repeated for a moment. Terminals with the kitty keyboard protocol report releases
+//! too, and keys are held exactly as long as they are down.
+
+use chip8_emulator::cli::{self, CpuOptions, PaletteOptions};
+use chip8_emulator::cpu::TIMER_HZ;
+use chip8_emulator::display::{TerminalSink, TextMode};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{cursor, execute, terminal};
+use std::env;
+use std::error::Error;
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+// The host time each frame lasts, the timers tick once per frame
+const FRAME_PERIOD: Duration = Duration::from_nanos(1_000_000_000 / TIMER_HZ as u64);
+
+// The number of frames a key stays held after it was last pressed, when the terminal does not
+// report releases. Long enough to bridge the gap before the terminal starts repeating the key
+const HOLD_FRAMES: u32 = 30;
+
+// Maps the left side of a QWERTY keyboard onto the hex keypad:
+//	1 2 3 4		1 2 3 C
+//	Q W E R		4 5 6 D
+//	A S D F		7 8 9 E
+//	Z X C V		A 0 B F
+const KEY_MAP: [(char, u8); 16] = [
+    ('1', 0x1),
+    ('2', 0x2),
+    ('3', 0x3),
+    ('4', 0xc),
+    ('q', 0x4),
+    ('w', 0x5),
+    ('e', 0x6),
+    ('r', 0xd),
+    ('a', 0x7),
+    ('s', 0x8),
+    ('d', 0x9),
+    ('f', 0xe),
+    ('z', 0xa),
+    ('x', 0x0),
+    ('c', 0xb),
+    ('v', 0xf),
+];
+
+// The rom to run, the platform, fonts and timing to run it with, the colours to show it in and
+// how to draw it
+struct Game {
+    path: PathBuf,
+    cpu: CpuOptions,
+    palette: PaletteOptions,
+    mode: TextMode,
+}
+
+// The terminal in raw mode on the alternate screen, put back how it was when dropped so an
+// error does not leave the shell unusable
+struct RawTerminal {
+    // Whether key releases were asked for, and have to be turned off again
+    releases: bool,
+}
+
+impl RawTerminal {
+    /// Switches the terminal to raw mode on the alternate screen with the cursor hidden, asking
+    /// for key releases if the terminal can report them
+    fn enter() -> io::Result<RawTerminal> {
+        terminal::enable_raw_mode()?;
+        let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        let mut stdout = io::stdout();
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        if releases {
+            execute!(
+                stdout,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        Ok(RawTerminal { releases })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.releases {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn main() -> ExitCode {
+    let game = match get_game(env::args().skip(1)) {
+        Ok(game) => game,
+        Err(message) => {
+            eprintln!("{message}");
+            eprintln!(
+                "usage: chip8_terminal {} {} [--braille] <rom>",
+                cli::CPU_USAGE,
+                cli::PALETTE_USAGE
+            );
+            cli::print_cpu_names();
+            cli::print_palette_names();
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(game) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the game in the terminal until it is quit, returning the error which stopped it early.
+/// The terminal is put back before the error is returned
+///	game - The rom and options to run it with
+fn run(game: Game) -> Result<(), Box<dyn Error>> {
+    let mut cpu = game.cpu.build(&game.path)?;
+    cpu.set_palette(game.palette.palette()?);
+    let instructions_per_frame = game.cpu.instructions_per_frame();
+
+    let raw = RawTerminal::enter()?;
+    let mut sink = TerminalSink::new(io::stdout(), game.mode);
+    // The number of frames each key has left to be held for, when the terminal does not report
+    // releases
+    let mut held = [0; KEY_MAP.len()];
+    let mut paused = false;
+    let mut next_frame = Instant::now();
+
+    loop {
+        // Handle the input which arrives before the next frame is due
+        while event::poll(next_frame.saturating_duration_since(Instant::now()))? {
+            let key = match event::read()? {
+                Event::Key(key) => key,
+                Event::Resize(..) => {
+                    execute!(io::stdout(), terminal::Clear(terminal::ClearType::All))?;
+                    sink.invalidate();
+                    continue;
+                }
+                _ => continue,
+            };
+            if is_quit(&key) {
+                return Ok(());
+            }
+            if key.code == KeyCode::Char('p') && key.kind == KeyEventKind::Press {
+                paused = !paused;
+            }
+            let KeyCode::Char(c) = key.code else {
+                continue;
+            };
+            let Some(i) = KEY_MAP
+                .iter()
+                .position(|&(code, _)| code == c.to_ascii_lowercase())
+            else {
+                continue;
+            };
+            if key.kind == KeyEventKind::Release {
+                cpu.release_key(KEY_MAP[i].1);
+            } else {
+                cpu.press_key(KEY_MAP[i].1);
+                held[i] = if raw.releases { u32::MAX } else { HOLD_FRAMES };
+            }
+        }
+
+        // Catch up if the host fell far behind, rather than running a burst of frames
+        next_frame = (next_frame + FRAME_PERIOD).max(Instant::now() - FRAME_PERIOD);
+        if !paused {
+            for (i, frames) in held.iter_mut().enumerate() {
+                if *frames == 1 {
+                    cpu.release_key(KEY_MAP[i].1);
+                }
+                *frames = frames.saturating_sub(1);
+            }
+            cpu.run_frame(instructions_per_frame)?;
+        }
+        cpu.present(&mut sink)?;
+    }
+}
+
+/// Returns true if the key quits the emulator, raw mode turns Ctrl+C into a key press
+///	key - The key which was pressed
+fn is_quit(key: &KeyEvent) -> bool {
+    key.kind == KeyEventKind::Press
+        && (key.code == KeyCode::Esc
+            || key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+/// Parses the command line arguments, after the program name, into the rom to run, the options
+/// to run it with and the text mode to draw it in
+///	args - The arguments to parse
+fn get_game(mut args: impl Iterator<Item = String>) -> Result<Game, String> {
+    let mut path = None;
+    let mut cpu = CpuOptions::default();
+    let mut palette = PaletteOptions::default();
+    let mut mode = TextMode::HalfBlock;
+    while let Some(arg) = args.next() {
+        if cpu.parse_arg(&arg, &mut args)? || palette.parse_arg(&arg, &mut args)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--braille" => mode = TextMode::Braille,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+    let path = path.ok_or("no rom given")?;
+    if !path.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    Ok(Game {
+        path,
+        cpu,
+        palette,
+        mode,
+    })
+}
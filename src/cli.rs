@@ -0,0 +1,238 @@
+//! The command line options the frontends share: the platform, fonts and timing to build the
+//! Cpu with, and the palette to show its screen in.
+//!
+//! Each frontend walks its own arguments, handing every one to `CpuOptions::parse_arg` and
+//! `PaletteOptions::parse_arg` before trying its own options.
+
+use crate::cpu::{BigFont, SmallFont};
+use crate::display::{Palette, Theme};
+use crate::{Cpu, CpuConfig, Platform};
+use std::path::{Path, PathBuf};
+
+// The number of instructions run every frame, unless a platform is picked
+pub const DEFAULT_INSTRUCTIONS_PER_FRAME: u32 = 10;
+
+// The usage of the options CpuOptions parses
+pub const CPU_USAGE: &str =
+    "[--platform NAME] [--font NAME] [--big-font NAME] [--font-file PATH] [--vip-timing]";
+
+// The usage of the options PaletteOptions parses
+pub const PALETTE_USAGE: &str = "[--palette NAME] [--colours HEX,...] [--palette-file PATH]";
+
+// The platform to run the rom as, the fonts to override the platform's with and whether to run
+// it with COSMAC VIP timing
+#[derive(Debug, Default)]
+pub struct CpuOptions {
+    pub platform: Option<Platform>,
+    pub small_font: Option<SmallFont>,
+    pub big_font: Option<BigFont>,
+    pub font_file: Option<PathBuf>,
+    pub vip_timing: bool,
+}
+
+impl CpuOptions {
+    /// Parses the argument if it is one of `--platform NAME`, `--font NAME`, `--big-font NAME`,
+    /// `--font-file PATH` or `--vip-timing`, returning whether it was, or an error if it is
+    /// missing its value or names something which does not exist
+    ///	arg - The argument to parse
+    ///	args - The arguments after it, the value is taken from these
+    pub fn parse_arg(
+        &mut self,
+        arg: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<bool, String> {
+        match arg {
+            "--platform" => {
+                let name = value(arg, args)?;
+                let platform = Platform::from_name(&name);
+                self.platform = Some(platform.ok_or(format!("unknown platform {name}"))?);
+            }
+            "--font" => {
+                let name = value(arg, args)?;
+                let font = SmallFont::from_name(&name);
+                self.small_font = Some(font.ok_or(format!("unknown font {name}"))?);
+            }
+            "--big-font" => {
+                let name = value(arg, args)?;
+                let font = BigFont::from_name(&name);
+                self.big_font = Some(font.ok_or(format!("unknown big font {name}"))?);
+            }
+            "--font-file" => self.font_file = Some(PathBuf::from(value(arg, args)?)),
+            "--vip-timing" => self.vip_timing = true,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Builds the Cpu for the platform with the fonts and timing picked, then loads the rom into
+    /// it. Returns an error naming the file if the font file or rom could not be loaded
+    ///	rom - The path of the rom to load
+    pub fn build(&self, rom: &Path) -> Result<Cpu, String> {
+        let mut config = self
+            .platform
+            .map_or_else(CpuConfig::default, Platform::config);
+        if let Some(small_font) = self.small_font {
+            config.font.small = *small_font.bytes();
+        }
+        if let Some(big_font) = self.big_font {
+            config.font.big = *big_font.bytes();
+        }
+        if let Some(path) = &self.font_file {
+            config
+                .font
+                .load_file(path)
+                .map_err(|error| format!("{}: {error}", path.display()))?;
+        }
+        let mut cpu = match self.platform {
+            Some(platform) => Cpu::with_platform_config(platform, config),
+            None => Cpu::new(config),
+        };
+        cpu.load_rom(rom)
+            .map_err(|error| format!("{}: {error}", rom.display()))?;
+        cpu.set_vip_timing(self.vip_timing);
+        Ok(cpu)
+    }
+
+    /// Returns the number of instructions to run every frame, the platform's if one was picked
+    pub fn instructions_per_frame(&self) -> u32 {
+        self.platform.map_or(
+            DEFAULT_INSTRUCTIONS_PER_FRAME,
+            Platform::instructions_per_frame,
+        )
+    }
+}
+
+// The colours to show the screen in, from a theme, a list of colours or a palette file
+#[derive(Debug, Default)]
+pub struct PaletteOptions {
+    pub theme: Option<Theme>,
+    pub colours: Option<Palette>,
+    pub palette_file: Option<PathBuf>,
+}
+
+impl PaletteOptions {
+    /// Parses the argument if it is one of `--palette NAME`, `--colours HEX,...` or
+    /// `--palette-file PATH`, returning whether it was, or an error if it is missing its value
+    /// or the value is not valid
+    ///	arg - The argument to parse
+    ///	args - The arguments after it, the value is taken from these
+    pub fn parse_arg(
+        &mut self,
+        arg: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<bool, String> {
+        match arg {
+            "--palette" => {
+                let name = value(arg, args)?;
+                let theme = Theme::from_name(&name);
+                self.theme = Some(theme.ok_or(format!("unknown palette {name}"))?);
+            }
+            "--colours" => {
+                let hex = value(arg, args)?;
+                self.colours = Some(Palette::from_hex(&hex).map_err(|error| error.to_string())?);
+            }
+            "--palette-file" => self.palette_file = Some(PathBuf::from(value(arg, args)?)),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Returns the palette picked. --colours wins over --palette-file, which wins over
+    /// --palette. Returns an error naming the file if the palette file could not be loaded
+    pub fn palette(&self) -> Result<Palette, String> {
+        if let Some(colours) = self.colours {
+            return Ok(colours);
+        }
+        match &self.palette_file {
+            Some(path) => {
+                Palette::load_file(path).map_err(|error| format!("{}: {error}", path.display()))
+            }
+            None => Ok(self.theme.map_or_else(Palette::default, Theme::palette)),
+        }
+    }
+}
+
+/// Returns the value following an option, or an error if there is none
+///	option - The option the value belongs to
+///	args - The arguments after the option
+pub fn value(option: &str, args: &mut impl Iterator<Item = String>) -> Result<String, String> {
+    args.next().ok_or(format!("{option} needs a value"))
+}
+
+/// Prints the names of the platforms and fonts CpuOptions accepts, to follow a usage line
+pub fn print_cpu_names() {
+    let names: Vec<&str> = Platform::ALL
+        .iter()
+        .map(|platform| platform.name())
+        .collect();
+    eprintln!("platforms: {}", names.join(", "));
+    let names: Vec<&str> = SmallFont::ALL.iter().map(|font| font.name()).collect();
+    eprintln!("fonts: {}", names.join(", "));
+    let names: Vec<&str> = BigFont::ALL.iter().map(|font| font.name()).collect();
+    eprintln!("big fonts: {}", names.join(", "));
+}
+
+/// Prints the names of the palettes PaletteOptions accepts, to follow a usage line
+pub fn print_palette_names() {
+    let names: Vec<&str> = Theme::ALL.iter().map(|theme| theme.name()).collect();
+    eprintln!("palettes: {}", names.join(", "));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the arguments with CpuOptions and PaletteOptions, returning the error for the
+    /// first one neither of them accepts
+    ///	args - The arguments to parse
+    fn parse(args: &[&str]) -> Result<(CpuOptions, PaletteOptions), String> {
+        let mut cpu = CpuOptions::default();
+        let mut palette = PaletteOptions::default();
+        let mut args = args.iter().map(|arg| arg.to_string());
+        while let Some(arg) = args.next() {
+            if !cpu.parse_arg(&arg, &mut args)? && !palette.parse_arg(&arg, &mut args)? {
+                return Err(format!("unknown option {arg}"));
+            }
+        }
+        Ok((cpu, palette))
+    }
+
+    #[test]
+    fn shared_options_are_parsed() {
+        let (cpu, palette) = parse(&[
+            "--platform",
+            "xo-chip",
+            "--vip-timing",
+            "--palette",
+            "amber",
+        ])
+        .unwrap();
+        assert_eq!(cpu.platform, Some(Platform::XoChip));
+        assert!(cpu.vip_timing);
+        assert_eq!(
+            cpu.instructions_per_frame(),
+            Platform::XoChip.instructions_per_frame()
+        );
+        assert_eq!(palette.palette().unwrap(), Theme::Amber.palette());
+    }
+
+    #[test]
+    fn bad_values_are_errors() {
+        assert_eq!(
+            parse(&["--platform", "nope"]).unwrap_err(),
+            "unknown platform nope"
+        );
+        assert_eq!(parse(&["--font"]).unwrap_err(), "--font needs a value");
+        assert_eq!(
+            parse(&["--colours", "zz"]).unwrap_err(),
+            "zz is not a hex colour"
+        );
+        assert_eq!(parse(&["--speed"]).unwrap_err(), "unknown option --speed");
+    }
+
+    #[test]
+    fn colours_win_over_the_theme() {
+        let (_, palette) = parse(&["--colours", "#000000,#00ff00", "--palette", "amber"]).unwrap();
+        assert_eq!(palette.palette().unwrap().rgba(1), [0, 0xff, 0, 0xff]);
+    }
+}
@@ -0,0 +1,134 @@
+// The number of pixels across each colour zone, the VP-590 colours the screen in 8 pixel
+// wide strips
+pub const ZONE_WIDTH: usize = 8;
+
+// The number of rows each zone set by BxY0 covers
+pub const ZONE_BLOCK_ROWS: usize = 4;
+
+// The number of background colours 02A0 cycles through
+const NUM_BACKGROUNDS: u8 = 4;
+
+// The foreground colour every zone starts with
+const DEFAULT_FOREGROUND: u8 = 1;
+
+// The RGBA colours of the VP-590 backgrounds: blue, black, green and red
+pub const VP590_BACKGROUNDS: [[u8; 4]; 4] = [
+    [0x00, 0x00, 0x80, 0xff],
+    [0x00, 0x00, 0x00, 0xff],
+    [0x00, 0x80, 0x00, 0xff],
+    [0x80, 0x00, 0x00, 0xff],
+];
+
+// The RGBA colours of the VP-590 foregrounds: black, red, blue, violet, green, yellow, aqua
+// and white
+pub const VP590_FOREGROUNDS: [[u8; 4]; 8] = [
+    [0x00, 0x00, 0x00, 0xff],
+    [0xff, 0x00, 0x00, 0xff],
+    [0x00, 0x00, 0xff, 0xff],
+    [0xff, 0x00, 0xff, 0xff],
+    [0x00, 0xff, 0x00, 0xff],
+    [0xff, 0xff, 0x00, 0xff],
+    [0x00, 0xff, 0xff, 0xff],
+    [0xff, 0xff, 0xff, 0xff],
+];
+
+// The CHIP-8X colour attribute layer over the screen. Pixels which are off show the background
+// colour, pixels which are on show the foreground colour of the zone they are in. Zones are
+// ZONE_WIDTH pixels wide and one row high
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColourMap {
+    // The background colour, an index into VP590_BACKGROUNDS
+    background: u8,
+    // The number of zones in each row
+    columns: usize,
+    // The number of rows of zones
+    rows: usize,
+    // The foreground colour of each zone, row by row, an index into VP590_FOREGROUNDS
+    zones: Vec<u8>,
+}
+
+impl ColourMap {
+    /// Returns a new colour map over a screen, with a blue background and red zones
+    ///	width - The number of pixels in each row of the screen
+    ///	height - The number of rows on the screen
+    pub fn new(width: usize, height: usize) -> ColourMap {
+        let columns = width.div_ceil(ZONE_WIDTH);
+        ColourMap {
+            background: 0,
+            columns,
+            rows: height,
+            zones: vec![DEFAULT_FOREGROUND; columns * height],
+        }
+    }
+
+    /// Returns the background colour, an index into VP590_BACKGROUNDS
+    pub fn background(&self) -> u8 {
+        self.background
+    }
+
+    /// Moves on to the next background colour, wrapping back to blue after red
+    pub fn next_background(&mut self) {
+        self.background = (self.background + 1) % NUM_BACKGROUNDS;
+    }
+
+    /// Returns the foreground colour of the pixel at (x, y), an index into VP590_FOREGROUNDS
+    ///	x - The column of the pixel
+    ///	y - The row of the pixel
+    pub fn foreground(&self, x: usize, y: usize) -> u8 {
+        self.zones[y * self.columns + x / ZONE_WIDTH]
+    }
+
+    /// Sets the foreground colour of the zones in columns and rows, ignoring any past the
+    /// edges of the screen
+    ///	columns - The zone columns to colour
+    ///	rows - The rows to colour
+    ///	colour - The foreground colour, only the lowest three bits are used
+    pub fn fill(
+        &mut self,
+        columns: std::ops::Range<usize>,
+        rows: std::ops::Range<usize>,
+        colour: u8,
+    ) {
+        for row in rows.start.min(self.rows)..rows.end.min(self.rows) {
+            let start = row * self.columns;
+            let zones = columns.start.min(self.columns)..columns.end.min(self.columns);
+            self.zones[start + zones.start..start + zones.end].fill(colour & 0x7);
+        }
+    }
+
+    /// Returns the RGBA colour the pixel at (x, y) is shown in
+    ///	x - The column of the pixel
+    ///	y - The row of the pixel
+    ///	on - Whether the pixel is on
+    pub fn rgba(&self, x: usize, y: usize, on: bool) -> [u8; 4] {
+        if on {
+            VP590_FOREGROUNDS[self.foreground(x, y) as usize]
+        } else {
+            VP590_BACKGROUNDS[self.background as usize]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn background_cycles_through_four_colours() {
+        let mut map = ColourMap::new(64, 32);
+        for background in [1, 2, 3, 0] {
+            map.next_background();
+            assert_eq!(map.background(), background);
+        }
+    }
+
+    #[test]
+    fn fill_clips_to_the_screen() {
+        let mut map = ColourMap::new(64, 32);
+        map.fill(6..10, 30..40, 0xf);
+        assert_eq!(map.foreground(48, 30), 7);
+        assert_eq!(map.foreground(63, 31), 7);
+        assert_eq!(map.foreground(47, 31), DEFAULT_FOREGROUND);
+        assert_eq!(map.foreground(63, 29), DEFAULT_FOREGROUND);
+    }
+}
@@ -0,0 +1,27 @@
+use crate::cpu::font::Font;
+use crate::cpu::{CALL_STACK_SIZE, MEM_SIZE, START_ADDRESS};
+
+// The sizes, addresses and fonts a Cpu is built with, which differ between interpreters. The
+// default is 4K of memory, 16 levels of call stack, roms starting at 0x200 and the Octo fonts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuConfig {
+    // The number of bytes of memory
+    pub memory_size: usize,
+    // The number of subroutine calls which can be nested, None for no limit
+    pub stack_depth: Option<usize>,
+    // The address roms are loaded at and run from
+    pub start_address: u16,
+    // The fonts stored in memory, and where they are stored
+    pub font: Font,
+}
+
+impl Default for CpuConfig {
+    fn default() -> CpuConfig {
+        CpuConfig {
+            memory_size: MEM_SIZE,
+            stack_depth: Some(CALL_STACK_SIZE),
+            start_address: START_ADDRESS,
+            font: Font::default(),
+        }
+    }
+}
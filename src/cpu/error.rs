@@ -0,0 +1,185 @@
+use crate::cpu::font::{BIG_FONT_BYTES, SMALL_FONT_BYTES};
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// The faults which stop the Cpu executing an instruction. Each carries the address of the
+// faulting instruction and, once it has been fetched, its opcode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CpuError {
+    // A call was made with every level of the call stack in use
+    StackOverflow { pc: u16, op_code: u16 },
+    // A return was made with nothing on the call stack
+    StackUnderflow { pc: u16, op_code: u16 },
+    // An instruction read or wrote memory past the end of memory
+    MemoryOutOfBounds { pc: u16, op_code: u16, addr: usize },
+    // The program counter ran past the end of memory, so no opcode could be fetched
+    PcOutOfBounds { pc: u16 },
+    // The opcode is not an instruction the Cpu knows
+    UnknownOpcode { pc: u16, op_code: u16 },
+}
+
+impl CpuError {
+    /// Returns the address of the instruction which faulted
+    pub fn pc(&self) -> u16 {
+        match *self {
+            CpuError::StackOverflow { pc, .. }
+            | CpuError::StackUnderflow { pc, .. }
+            | CpuError::MemoryOutOfBounds { pc, .. }
+            | CpuError::PcOutOfBounds { pc }
+            | CpuError::UnknownOpcode { pc, .. } => pc,
+        }
+    }
+
+    /// Returns the opcode of the instruction which faulted, if it could be fetched
+    pub fn op_code(&self) -> Option<u16> {
+        match *self {
+            CpuError::StackOverflow { op_code, .. }
+            | CpuError::StackUnderflow { op_code, .. }
+            | CpuError::MemoryOutOfBounds { op_code, .. }
+            | CpuError::UnknownOpcode { op_code, .. } => Some(op_code),
+            CpuError::PcOutOfBounds { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CpuError::StackOverflow { pc, op_code } => {
+                write!(f, "stack overflow at {pc:#05x} ({op_code:#06x})")
+            }
+            CpuError::StackUnderflow { pc, op_code } => {
+                write!(f, "stack underflow at {pc:#05x} ({op_code:#06x})")
+            }
+            CpuError::MemoryOutOfBounds { pc, op_code, addr } => write!(
+                f,
+                "memory access to {addr:#x} out of bounds at {pc:#05x} ({op_code:#06x})"
+            ),
+            CpuError::PcOutOfBounds { pc } => {
+                write!(f, "program counter {pc:#05x} out of bounds")
+            }
+            CpuError::UnknownOpcode { pc, op_code } => {
+                write!(f, "unknown opcode {op_code:#06x} at {pc:#05x}")
+            }
+        }
+    }
+}
+
+impl Error for CpuError {}
+
+// The faults an instruction can raise while it executes. The Cpu adds the address and opcode
+// of the instruction to turn one into a CpuError
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    StackOverflow,
+    StackUnderflow,
+    MemoryOutOfBounds { addr: usize },
+    UnknownOpcode,
+}
+
+impl Fault {
+    /// Returns the CpuError for this fault, raised by the instruction op_code at pc
+    ///	pc - The address of the faulting instruction
+    ///	op_code - The opcode of the faulting instruction
+    pub fn at(self, pc: u16, op_code: u16) -> CpuError {
+        match self {
+            Fault::StackOverflow => CpuError::StackOverflow { pc, op_code },
+            Fault::StackUnderflow => CpuError::StackUnderflow { pc, op_code },
+            Fault::MemoryOutOfBounds { addr } => CpuError::MemoryOutOfBounds { pc, op_code, addr },
+            Fault::UnknownOpcode => CpuError::UnknownOpcode { pc, op_code },
+        }
+    }
+}
+
+// What the Cpu does when an instruction faults
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FaultPolicy {
+    // Stop executing. The fault is returned from this and every later cycle until resume
+    #[default]
+    Halt,
+    // Return the fault so the host can log it, then carry on with the next instruction
+    Report,
+    // Skip the faulting instruction without telling the host
+    Ignore,
+}
+
+// The reasons a rom could not be loaded into memory
+#[derive(Debug)]
+pub enum RomError {
+    // The rom could not be read
+    Io(io::Error),
+    // The rom has no bytes in it
+    Empty,
+    // The rom does not fit in the memory after the start address
+    TooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::Io(error) => write!(f, "could not read rom: {error}"),
+            RomError::Empty => write!(f, "rom is empty"),
+            RomError::TooLarge { size, max } => {
+                write!(
+                    f,
+                    "rom is {size} bytes, but at most {max} bytes fit in memory"
+                )
+            }
+        }
+    }
+}
+
+impl Error for RomError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RomError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RomError {
+    fn from(error: io::Error) -> RomError {
+        RomError::Io(error)
+    }
+}
+
+// The reasons a custom font could not be loaded
+#[derive(Debug)]
+pub enum FontError {
+    // The font could not be read
+    Io(io::Error),
+    // The font is neither a small font nor a small font followed by a big font
+    WrongSize { size: usize },
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FontError::Io(error) => write!(f, "could not read font: {error}"),
+            FontError::WrongSize { size } => {
+                write!(
+                    f,
+                    "font is {size} bytes, but must be {SMALL_FONT_BYTES} or {} bytes",
+                    SMALL_FONT_BYTES + BIG_FONT_BYTES
+                )
+            }
+        }
+    }
+}
+
+impl Error for FontError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FontError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FontError {
+    fn from(error: io::Error) -> FontError {
+        FontError::Io(error)
+    }
+}
@@ -0,0 +1,344 @@
+use crate::cpu::error::FontError;
+use std::fs;
+use std::path::Path;
+
+// The number of bytes in a small font, 5 for each of the digits 0 .. F
+pub const SMALL_FONT_BYTES: usize = 80;
+
+// The number of bytes in a big font, 10 for each of the digits 0 .. F
+pub const BIG_FONT_BYTES: usize = 160;
+
+// The address the small font is stored at, unless the platform picks another
+pub const FONT_SET_START: usize = 0x50;
+
+// The address the big font is stored at, unless the platform picks another, right after the
+// small font
+pub const BIG_FONT_SET_START: usize = FONT_SET_START + SMALL_FONT_BYTES;
+
+// The small font of the COSMAC VIP interpreter
+const VIP_FONT: [u8; SMALL_FONT_BYTES] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// The small font of the DREAM 6800 interpreter, three pixels wide
+const DREAM6800_FONT: [u8; SMALL_FONT_BYTES] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x40, 0x40, 0x40, 0x40, 0x40, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+// The small font of the ETI-660 interpreter, three pixels wide
+const ETI660_FONT: [u8; SMALL_FONT_BYTES] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x20, 0x20, 0x20, 0x20, 0x20, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0xA0, 0xA0, 0xE0, 0x20, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+// The small font of the fishNchips interpreter, with rounded digits
+const FISH_N_CHIPS_FONT: [u8; SMALL_FONT_BYTES] = [
+    0x60, 0xA0, 0xA0, 0xA0, 0xC0, // 0
+    0x40, 0xC0, 0x40, 0x40, 0xE0, // 1
+    0xC0, 0x20, 0x40, 0x80, 0xE0, // 2
+    0xC0, 0x20, 0x40, 0x20, 0xC0, // 3
+    0x20, 0xA0, 0xE0, 0x20, 0x20, // 4
+    0xE0, 0x80, 0xC0, 0x20, 0xC0, // 5
+    0x40, 0x80, 0xC0, 0xA0, 0x40, // 6
+    0xE0, 0x20, 0x60, 0x40, 0x40, // 7
+    0x40, 0xA0, 0x40, 0xA0, 0x40, // 8
+    0x40, 0xA0, 0x60, 0x20, 0x40, // 9
+    0x40, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xC0, 0xA0, 0xC0, // B
+    0x60, 0x80, 0x80, 0x80, 0x60, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xC0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+// The small font of Octo, which most modern interpreters share
+const OCTO_FONT: [u8; SMALL_FONT_BYTES] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// The 8x10 font of SUPER-CHIP 1.1, which only has the digits 0 .. 9
+const SUPER_CHIP_BIG_FONT: [u8; BIG_FONT_BYTES] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // A
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // B
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // C
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // D
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // E
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // F
+];
+
+// The 8x10 font of Octo, with all of the digits 0 .. F
+const OCTO_BIG_FONT: [u8; BIG_FONT_BYTES] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+// The small fonts Fx29 can point at, as drawn by the interpreters they come from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SmallFont {
+    // The COSMAC VIP interpreter
+    Vip,
+    // The DREAM 6800 interpreter
+    Dream6800,
+    // The ETI-660 interpreter
+    Eti660,
+    // The fishNchips interpreter
+    FishNChips,
+    // Octo, which most modern interpreters share
+    #[default]
+    Octo,
+}
+
+impl SmallFont {
+    // Every small font, in the order they are listed to users
+    pub const ALL: [SmallFont; 5] = [
+        SmallFont::Vip,
+        SmallFont::Dream6800,
+        SmallFont::Eti660,
+        SmallFont::FishNChips,
+        SmallFont::Octo,
+    ];
+
+    /// Returns the name the font is picked by on the command line, e.g. "dream6800"
+    pub fn name(self) -> &'static str {
+        match self {
+            SmallFont::Vip => "vip",
+            SmallFont::Dream6800 => "dream6800",
+            SmallFont::Eti660 => "eti-660",
+            SmallFont::FishNChips => "fishnchips",
+            SmallFont::Octo => "octo",
+        }
+    }
+
+    /// Returns the font with the name, or None if there is no such font
+    ///	name - The name of the font, as returned by name
+    pub fn from_name(name: &str) -> Option<SmallFont> {
+        SmallFont::ALL.into_iter().find(|font| font.name() == name)
+    }
+
+    /// Returns the 5 bytes of each digit 0 .. F
+    pub fn bytes(self) -> &'static [u8; SMALL_FONT_BYTES] {
+        match self {
+            SmallFont::Vip => &VIP_FONT,
+            SmallFont::Dream6800 => &DREAM6800_FONT,
+            SmallFont::Eti660 => &ETI660_FONT,
+            SmallFont::FishNChips => &FISH_N_CHIPS_FONT,
+            SmallFont::Octo => &OCTO_FONT,
+        }
+    }
+}
+
+// The big fonts the SUPER-CHIP Fx30 can point at
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BigFont {
+    // SUPER-CHIP 1.1, which only has the digits 0 .. 9
+    SuperChip,
+    // Octo, with all of the digits 0 .. F
+    #[default]
+    Octo,
+}
+
+impl BigFont {
+    // Every big font, in the order they are listed to users
+    pub const ALL: [BigFont; 2] = [BigFont::SuperChip, BigFont::Octo];
+
+    /// Returns the name the font is picked by on the command line, e.g. "schip"
+    pub fn name(self) -> &'static str {
+        match self {
+            BigFont::SuperChip => "schip",
+            BigFont::Octo => "octo",
+        }
+    }
+
+    /// Returns the font with the name, or None if there is no such font
+    ///	name - The name of the font, as returned by name
+    pub fn from_name(name: &str) -> Option<BigFont> {
+        BigFont::ALL.into_iter().find(|font| font.name() == name)
+    }
+
+    /// Returns the 10 bytes of each digit 0 .. F
+    pub fn bytes(self) -> &'static [u8; BIG_FONT_BYTES] {
+        match self {
+            BigFont::SuperChip => &SUPER_CHIP_BIG_FONT,
+            BigFont::Octo => &OCTO_BIG_FONT,
+        }
+    }
+}
+
+// The small and big fonts a Cpu stores in memory, and the addresses it stores them at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Font {
+    // The 5 bytes of each digit 0 .. F, pointed at by Fx29
+    pub small: [u8; SMALL_FONT_BYTES],
+    // The 10 bytes of each digit 0 .. F, pointed at by Fx30
+    pub big: [u8; BIG_FONT_BYTES],
+    // The address the small font is stored at
+    pub small_address: u16,
+    // The address the big font is stored at
+    pub big_address: u16,
+}
+
+impl Default for Font {
+    fn default() -> Font {
+        Font::new(SmallFont::default(), BigFont::default())
+    }
+}
+
+impl Font {
+    /// Returns the fonts stored at the usual addresses, the small font at 0x50 and the big font
+    /// right after it
+    ///	small - The small font
+    ///	big - The big font
+    pub fn new(small: SmallFont, big: BigFont) -> Font {
+        Font {
+            small: *small.bytes(),
+            big: *big.bytes(),
+            small_address: FONT_SET_START as u16,
+            big_address: BIG_FONT_SET_START as u16,
+        }
+    }
+
+    /// Replaces the fonts with the bytes of a custom font. 80 bytes replace the small font, 240
+    /// bytes replace the small font and then the big font
+    ///	bytes - The bytes of the custom font
+    pub fn load_bytes(&mut self, bytes: &[u8]) -> Result<(), FontError> {
+        match bytes.len() {
+            SMALL_FONT_BYTES => self.small.copy_from_slice(bytes),
+            len if len == SMALL_FONT_BYTES + BIG_FONT_BYTES => {
+                let (small, big) = bytes.split_at(SMALL_FONT_BYTES);
+                self.small.copy_from_slice(small);
+                self.big.copy_from_slice(big);
+            }
+            size => return Err(FontError::WrongSize { size }),
+        }
+        Ok(())
+    }
+
+    /// Replaces the fonts with the custom font stored at path, as load_bytes does
+    ///	path - The path which we should load the font from
+    pub fn load_file(&mut self, path: &Path) -> Result<(), FontError> {
+        self.load_bytes(&fs::read(path)?)
+    }
+
+    /// Returns the first address after both fonts
+    pub(crate) fn end(&self) -> usize {
+        let small_end = self.small_address as usize + SMALL_FONT_BYTES;
+        let big_end = self.big_address as usize + BIG_FONT_BYTES;
+        small_end.max(big_end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for font in SmallFont::ALL {
+            assert_eq!(SmallFont::from_name(font.name()), Some(font));
+        }
+        for font in BigFont::ALL {
+            assert_eq!(BigFont::from_name(font.name()), Some(font));
+        }
+        assert_eq!(SmallFont::from_name("chip-8"), None);
+    }
+
+    #[test]
+    fn custom_fonts_must_be_small_or_small_and_big() {
+        let mut font = Font::new(SmallFont::Vip, BigFont::SuperChip);
+        font.load_bytes(&[0x11; SMALL_FONT_BYTES]).unwrap();
+        assert_eq!(font.small, [0x11; SMALL_FONT_BYTES]);
+        assert_eq!(font.big, SUPER_CHIP_BIG_FONT);
+
+        font.load_bytes(&[0x22; SMALL_FONT_BYTES + BIG_FONT_BYTES])
+            .unwrap();
+        assert_eq!(font.big, [0x22; BIG_FONT_BYTES]);
+
+        assert!(matches!(
+            font.load_bytes(&[0; 100]),
+            Err(FontError::WrongSize { size: 100 })
+        ));
+    }
+}
@@ -0,0 +1,233 @@
+// The bit plane which plain CHIP-8 and SUPER-CHIP programs draw to
+pub const DEFAULT_PLANE: u8 = 0b01;
+
+// The bit planes XO-CHIP programs can draw to
+pub const ALL_PLANES: u8 = 0b11;
+
+// Represents the pixels on the screen, at whatever resolution the screen is currently in. Each
+// pixel holds one bit per bit plane, XO-CHIP draws to two planes, everything else only to the
+// first
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Graphics {
+    // The number of pixels in each row
+    width: usize,
+    // The number of rows
+    height: usize,
+    // The pixels, row by row, bit n is set for pixels which are on in plane n + 1
+    pixels: Vec<u8>,
+}
+
+impl Graphics {
+    /// Returns a new blank screen
+    ///	width - The number of pixels in each row
+    ///	height - The number of rows
+    pub fn new(width: usize, height: usize) -> Graphics {
+        Graphics {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    /// Returns the number of pixels in each row
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns true if the pixel at (x, y) is on in any plane
+    ///	x - The column of the pixel, must be less than the width
+    ///	y - The row of the pixel, must be less than the height
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.planes(x, y) != 0
+    }
+
+    /// Returns the planes the pixel at (x, y) is on in, bit n set for plane n + 1
+    ///	x - The column of the pixel, must be less than the width
+    ///	y - The row of the pixel, must be less than the height
+    pub fn planes(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Sets the planes the pixel at (x, y) is on in
+    ///	x - The column of the pixel, must be less than the width
+    ///	y - The row of the pixel, must be less than the height
+    ///	planes - The planes the pixel should be on in, bit n set for plane n + 1
+    pub fn set(&mut self, x: usize, y: usize, planes: u8) {
+        self.pixels[y * self.width + x] = planes;
+    }
+
+    /// Flips the pixel at (x, y) in the plane, returning true if it was on and has been turned
+    /// off
+    ///	x - The column of the pixel, must be less than the width
+    ///	y - The row of the pixel, must be less than the height
+    ///	plane - The single plane bit to flip
+    pub fn toggle(&mut self, x: usize, y: usize, plane: u8) -> bool {
+        let pixel = &mut self.pixels[y * self.width + x];
+        *pixel ^= plane;
+        *pixel & plane == 0
+    }
+
+    /// Returns the rows of the screen, top to bottom, each pixel holding its plane bits
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.pixels.chunks_exact(self.width)
+    }
+
+    /// Turns every pixel off in the planes
+    ///	planes - The planes to clear
+    pub fn clear(&mut self, planes: u8) {
+        self.pixels.iter_mut().for_each(|pixel| *pixel &= !planes);
+    }
+
+    /// Changes the resolution of the screen, turning every pixel off
+    ///	width - The new number of pixels in each row
+    ///	height - The new number of rows
+    pub fn resize(&mut self, width: usize, height: usize) {
+        *self = Graphics::new(width, height);
+    }
+
+    /// Changes the resolution of the screen, keeping what is on it by scaling it to the new
+    /// resolution. Each pixel takes the planes of the old pixel it covers the top left of
+    ///	width - The new number of pixels in each row
+    ///	height - The new number of rows
+    pub fn rescale(&mut self, width: usize, height: usize) {
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+        let mut scaled = Graphics::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let planes = self.planes(x * self.width / width, y * self.height / height);
+                scaled.set(x, y, planes);
+            }
+        }
+        *self = scaled;
+    }
+
+    /// Moves every row of the planes down by n, blank rows come in at the top
+    ///	n - The number of rows to scroll by
+    ///	planes - The planes to scroll
+    pub fn scroll_down(&mut self, n: usize, planes: u8) {
+        let n = n.min(self.height) * self.width;
+        for i in (0..self.pixels.len()).rev() {
+            let from = i.checked_sub(n).map_or(0, |from| self.pixels[from]);
+            self.pixels[i] = self.pixels[i] & !planes | from & planes;
+        }
+    }
+
+    /// Moves every row of the planes up by n, blank rows come in at the bottom
+    ///	n - The number of rows to scroll by
+    ///	planes - The planes to scroll
+    pub fn scroll_up(&mut self, n: usize, planes: u8) {
+        let n = n.min(self.height) * self.width;
+        for i in 0..self.pixels.len() {
+            let from = self.pixels.get(i + n).copied().unwrap_or(0);
+            self.pixels[i] = self.pixels[i] & !planes | from & planes;
+        }
+    }
+
+    /// Moves every column of the planes right by n, blank columns come in on the left
+    ///	n - The number of columns to scroll by
+    ///	planes - The planes to scroll
+    pub fn scroll_right(&mut self, n: usize, planes: u8) {
+        for row in self.pixels.chunks_exact_mut(self.width) {
+            for i in (0..row.len()).rev() {
+                let from = i.checked_sub(n).map_or(0, |from| row[from]);
+                row[i] = row[i] & !planes | from & planes;
+            }
+        }
+    }
+
+    /// Moves every column of the planes left by n, blank columns come in on the right
+    ///	n - The number of columns to scroll by
+    ///	planes - The planes to scroll
+    pub fn scroll_left(&mut self, n: usize, planes: u8) {
+        for row in self.pixels.chunks_exact_mut(self.width) {
+            for i in 0..row.len() {
+                let from = row.get(i + n).copied().unwrap_or(0);
+                row[i] = row[i] & !planes | from & planes;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_reports_pixels_turned_off() {
+        let mut graphics = Graphics::new(8, 4);
+        assert!(!graphics.toggle(3, 2, DEFAULT_PLANE));
+        assert!(graphics.get(3, 2));
+        assert!(graphics.toggle(3, 2, DEFAULT_PLANE));
+        assert!(!graphics.get(3, 2));
+    }
+
+    #[test]
+    fn planes_are_independent() {
+        let mut graphics = Graphics::new(8, 4);
+        graphics.toggle(1, 1, 0b01);
+        assert!(!graphics.toggle(1, 1, 0b10));
+        assert_eq!(graphics.planes(1, 1), 0b11);
+        graphics.clear(0b01);
+        assert_eq!(graphics.planes(1, 1), 0b10);
+    }
+
+    #[test]
+    fn scrolls_fill_with_blank_pixels() {
+        let mut graphics = Graphics::new(8, 4);
+        graphics.set(0, 0, DEFAULT_PLANE);
+        graphics.set(7, 3, DEFAULT_PLANE);
+
+        graphics.scroll_down(1, DEFAULT_PLANE);
+        assert!(graphics.get(0, 1));
+        assert!(graphics.rows().next().unwrap().iter().all(|&on| on == 0));
+
+        graphics.scroll_right(4, DEFAULT_PLANE);
+        assert!(graphics.get(4, 1));
+        assert!(!graphics.get(0, 1));
+
+        graphics.scroll_left(4, DEFAULT_PLANE);
+        assert!(graphics.get(0, 1));
+        assert!(!graphics.get(4, 1));
+
+        graphics.scroll_up(1, DEFAULT_PLANE);
+        assert!(graphics.get(0, 0));
+        assert!(graphics.rows().last().unwrap().iter().all(|&on| on == 0));
+    }
+
+    #[test]
+    fn scrolls_leave_other_planes_alone() {
+        let mut graphics = Graphics::new(8, 4);
+        graphics.set(2, 2, 0b11);
+        graphics.scroll_down(1, 0b10);
+        assert_eq!(graphics.planes(2, 2), 0b01);
+        assert_eq!(graphics.planes(2, 3), 0b10);
+    }
+
+    #[test]
+    fn rescale_keeps_the_screen() {
+        let mut graphics = Graphics::new(64, 32);
+        graphics.set(1, 1, DEFAULT_PLANE);
+        graphics.rescale(128, 64);
+        assert!((2..4).all(|y| (2..4).all(|x| graphics.get(x, y))));
+        assert_eq!(graphics.rows().flatten().filter(|&&on| on != 0).count(), 4);
+        graphics.rescale(64, 32);
+        assert!(graphics.get(1, 1));
+        assert_eq!(graphics.rows().flatten().filter(|&&on| on != 0).count(), 1);
+    }
+
+    #[test]
+    fn resize_clears_the_screen() {
+        let mut graphics = Graphics::new(64, 32);
+        graphics.set(1, 1, DEFAULT_PLANE);
+        graphics.resize(128, 64);
+        assert_eq!((graphics.width(), graphics.height()), (128, 64));
+        assert!(graphics.rows().flatten().all(|&on| on == 0));
+    }
+}
@@ -0,0 +1,638 @@
+use std::fmt;
+
+// Represents a decoded instruction, with its operands pulled out of the opcode.
+// x and y are register numbers, n is a nibble, nn is a byte and nnn is an address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    // 0nnn - Calls a machine code routine, ignored by interpreters
+    Sys { nnn: u16 },
+    // 0010 - Turns MegaChip mode off (MegaChip)
+    MegaOff,
+    // 0011 - Turns MegaChip mode on (MegaChip)
+    MegaOn,
+    // 01nn nnnn - Sets I to the 24 bit address nn followed by the next two bytes (MegaChip)
+    LdILongByte { nn: u8 },
+    // 02nn - Loads nn palette colours from I (MegaChip)
+    LdPalByte { nn: u8 },
+    // 03nn - Sets the sprite width to nn, 0 meaning 256 (MegaChip)
+    SprWidthByte { nn: u8 },
+    // 04nn - Sets the sprite height to nn, 0 meaning 256 (MegaChip)
+    SprHeightByte { nn: u8 },
+    // 05nn - Sets the screen alpha to nn (MegaChip)
+    AlphaByte { nn: u8 },
+    // 060n - Plays the digitized sound at I, looping if n is 0 (MegaChip)
+    DigiSndNibble { n: u8 },
+    // 0700 - Stops the digitized sound (MegaChip)
+    StopSnd,
+    // 080n - Sets the sprite blend mode to n (MegaChip)
+    BlendNibble { n: u8 },
+    // 09nn - Sets the collision colour to nn (MegaChip)
+    CcolByte { nn: u8 },
+    // 0151 - Waits until the delay timer reaches 0 (CHIP-8E)
+    WaitDt,
+    // 0188 - Skips the next instruction (CHIP-8E)
+    Skip,
+    // 02A0 - Moves on to the next background colour (CHIP-8X)
+    NextBackground,
+    // 00E0 - Clears the screen, 0230 on the two-page hires CHIP-8 interpreter
+    Cls,
+    // 00EE - Returns from a subroutine
+    Ret,
+    // 00Cn - Scrolls the screen down n rows (SUPER-CHIP)
+    ScdNibble { n: u8 },
+    // 00Dn - Scrolls the screen up n rows (XO-CHIP), 00Bn on MegaChip
+    ScuNibble { n: u8 },
+    // 00FB - Scrolls the screen right 4 columns (SUPER-CHIP)
+    Scr,
+    // 00FC - Scrolls the screen left 4 columns (SUPER-CHIP)
+    Scl,
+    // 00FD - Exits the interpreter (SUPER-CHIP), 00ED on CHIP-8E
+    Exit,
+    // 00FE - Switches to the low resolution screen (SUPER-CHIP)
+    Low,
+    // 00FF - Switches to the high resolution screen (SUPER-CHIP)
+    High,
+    // 1nnn - Jumps to nnn
+    JpAddr { nnn: u16 },
+    // 2nnn - Calls the subroutine at nnn
+    CallAddr { nnn: u16 },
+    // 3xnn - Skips the next instruction if Vx == nn
+    SeVxByte { x: u8, nn: u8 },
+    // 4xnn - Skips the next instruction if Vx != nn
+    SneVxByte { x: u8, nn: u8 },
+    // 5xy0 - Skips the next instruction if Vx == Vy
+    SeVxVy { x: u8, y: u8 },
+    // 5xy1 - Skips the next instruction if Vx > Vy (CHIP-8E)
+    SgtVxVy { x: u8, y: u8 },
+    // 5xy1 - Adds each nibble of Vy to the same nibble of Vx, wrapping each at 8 (CHIP-8X)
+    AddNibblesVxVy { x: u8, y: u8 },
+    // 5xy2 - Stores Vx .. Vy in memory starting at I, in either order (XO-CHIP and CHIP-8E)
+    LdIVxVy { x: u8, y: u8 },
+    // 5xy3 - Reads Vx .. Vy from memory starting at I, in either order (XO-CHIP and CHIP-8E)
+    LdVxVyI { x: u8, y: u8 },
+    // 6xnn - Sets Vx to nn
+    LdVxByte { x: u8, nn: u8 },
+    // 7xnn - Adds nn to Vx
+    AddVxByte { x: u8, nn: u8 },
+    // 8xy0 - Sets Vx to Vy
+    LdVxVy { x: u8, y: u8 },
+    // 8xy1 - Sets Vx to Vx OR Vy
+    OrVxVy { x: u8, y: u8 },
+    // 8xy2 - Sets Vx to Vx AND Vy
+    AndVxVy { x: u8, y: u8 },
+    // 8xy3 - Sets Vx to Vx XOR Vy
+    XorVxVy { x: u8, y: u8 },
+    // 8xy4 - Adds Vy to Vx, VF is the carry
+    AddVxVy { x: u8, y: u8 },
+    // 8xy5 - Subtracts Vy from Vx, VF is NOT borrow
+    SubVxVy { x: u8, y: u8 },
+    // 8xy6 - Shifts right once, VF is the bit shifted out
+    ShrVxVy { x: u8, y: u8 },
+    // 8xy7 - Sets Vx to Vy - Vx, VF is NOT borrow
+    SubnVxVy { x: u8, y: u8 },
+    // 8xyE - Shifts left once, VF is the bit shifted out
+    ShlVxVy { x: u8, y: u8 },
+    // 9xy0 - Skips the next instruction if Vx != Vy
+    SneVxVy { x: u8, y: u8 },
+    // Annn - Sets I to nnn
+    LdIAddr { nnn: u16 },
+    // Bnnn - Jumps to nnn + V0
+    JpV0Addr { nnn: u16 },
+    // BBnn - Jumps back nn bytes from this instruction (CHIP-8E)
+    JpBackByte { nn: u8 },
+    // BFnn - Jumps forward nn bytes from this instruction (CHIP-8E)
+    JpFwdByte { nn: u8 },
+    // Bxyn - Sets the foreground colour of the zones named by Vx and Vx + 1 to Vy (CHIP-8X)
+    ColVxVyNibble { x: u8, y: u8, n: u8 },
+    // Cxnn - Sets Vx to a random byte AND nn
+    RndVxByte { x: u8, nn: u8 },
+    // Dxyn - Draws the n byte sprite at I to (Vx, Vy), VF is collision. On SUPER-CHIP, Dxy0
+    // draws a 16x16 sprite
+    DrwVxVyNibble { x: u8, y: u8, n: u8 },
+    // Ex9E - Skips the next instruction if the key in Vx is pressed
+    SkpVx { x: u8 },
+    // ExA1 - Skips the next instruction if the key in Vx is not pressed
+    SknpVx { x: u8 },
+    // ExF2 - Skips the next instruction if the key in Vx is pressed on keypad 2 (CHIP-8X)
+    Skp2Vx { x: u8 },
+    // ExF5 - Skips the next instruction if the key in Vx is not pressed on keypad 2 (CHIP-8X)
+    Sknp2Vx { x: u8 },
+    // F000 nnnn - Sets I to the 16 bit address in the next two bytes (XO-CHIP)
+    LdILong,
+    // Fn01 - Selects the bit planes n which are drawn, cleared and scrolled (XO-CHIP)
+    Plane { n: u8 },
+    // F002 - Loads the 16 byte audio pattern from memory starting at I (XO-CHIP)
+    Audio,
+    // Fx3A - Sets the pitch the audio pattern is played at to Vx (XO-CHIP)
+    PitchVx { x: u8 },
+    // Fx03 - Writes Vx to the output port (CHIP-8E), FxF8 on CHIP-8I
+    OutVx { x: u8 },
+    // FxE7 - Sets Vx to the input port (CHIP-8E)
+    InVx { x: u8 },
+    // FxE3 - Waits for input, then sets Vx to the input port (CHIP-8E), FxFB on CHIP-8I
+    InWaitVx { x: u8 },
+    // Fx1B - Skips the next Vx bytes (CHIP-8E)
+    SkipVx { x: u8 },
+    // Fx4F - Sets the delay timer to Vx, then waits until it reaches 0 (CHIP-8E)
+    LdDtVxWait { x: u8 },
+    // Fx07 - Sets Vx to the delay timer
+    LdVxDt { x: u8 },
+    // Fx0A - Waits for a key press and release, then stores the key in Vx
+    LdVxK { x: u8 },
+    // Fx15 - Sets the delay timer to Vx
+    LdDtVx { x: u8 },
+    // Fx18 - Sets the sound timer to Vx
+    LdStVx { x: u8 },
+    // Fx1E - Adds Vx to I
+    AddIVx { x: u8 },
+    // Fx29 - Sets I to the font sprite for the digit in Vx
+    LdFVx { x: u8 },
+    // Fx30 - Sets I to the big font sprite for the digit in Vx (SUPER-CHIP)
+    LdHfVx { x: u8 },
+    // Fx33 - Stores the BCD representation of Vx at I, I + 1 and I + 2
+    LdBVx { x: u8 },
+    // Fx55 - Stores V0 .. Vx in memory starting at I
+    LdIVx { x: u8 },
+    // Fx65 - Reads V0 .. Vx from memory starting at I
+    LdVxI { x: u8 },
+    // Fx75 - Stores V0 .. Vx in the RPL user flags (SUPER-CHIP)
+    LdRVx { x: u8 },
+    // Fx85 - Reads V0 .. Vx from the RPL user flags (SUPER-CHIP)
+    LdVxR { x: u8 },
+    // Any opcode which is not a known instruction
+    Unknown(u16),
+}
+
+// The families of instructions a Cpu can decode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InstructionSet {
+    // The original CHIP-8 instructions
+    #[default]
+    Chip8,
+    // CHIP-8 plus the CHIP-8E skip, jump, memory and port instructions
+    Chip8E,
+    // CHIP-8 plus the CHIP-8I port instructions
+    Chip8I,
+    // CHIP-8 patched for the two-page 64x64 screen, where 0230 clears the whole screen
+    HiresChip8,
+    // CHIP-8 plus the CHIP-8X colour and second keypad instructions
+    Chip8X,
+    // CHIP-8 plus the SUPER-CHIP 1.1 instructions
+    SuperChip,
+    // SUPER-CHIP plus the MegaChip 256x192 palette screen instructions
+    MegaChip,
+    // SUPER-CHIP plus the XO-CHIP instructions, with 64K of memory and two bit planes
+    XoChip,
+}
+
+impl InstructionSet {
+    /// Returns true if the SUPER-CHIP instructions can be decoded
+    pub fn has_super_chip(self) -> bool {
+        matches!(
+            self,
+            InstructionSet::SuperChip | InstructionSet::XoChip | InstructionSet::MegaChip
+        )
+    }
+
+    /// Returns true if the CHIP-8E instructions can be decoded
+    pub fn has_chip8e(self) -> bool {
+        matches!(self, InstructionSet::Chip8E)
+    }
+
+    /// Returns true if the CHIP-8I instructions can be decoded
+    pub fn has_chip8i(self) -> bool {
+        matches!(self, InstructionSet::Chip8I)
+    }
+
+    /// Returns true if the two-page hires CHIP-8 instructions can be decoded
+    pub fn has_hires_chip8(self) -> bool {
+        matches!(self, InstructionSet::HiresChip8)
+    }
+
+    /// Returns true if the MegaChip instructions can be decoded
+    pub fn has_mega_chip(self) -> bool {
+        matches!(self, InstructionSet::MegaChip)
+    }
+
+    /// Returns true if the CHIP-8X instructions can be decoded
+    pub fn has_chip8x(self) -> bool {
+        matches!(self, InstructionSet::Chip8X)
+    }
+
+    /// Returns true if the XO-CHIP instructions can be decoded
+    pub fn has_xo_chip(self) -> bool {
+        matches!(self, InstructionSet::XoChip)
+    }
+}
+
+// Represents each half byte (a nibble) in a 2-byte number
+type Nibbles = [u8; 4];
+
+// Masks out the first nibble from a 2-byte numbers
+const NIBBLE_ONE_MASK: u16 = 0xf000;
+// Masks out the second nibble from a 2-byte numbers
+const NIBBLE_TWO_MASK: u16 = 0x0f00;
+// Masks out the third nibble from a 2-byte numbers
+const NIBBLE_THREE_MASK: u16 = 0x00f0;
+// Masks out the fourth nibble from a 2-byte numbers
+const NIBBLE_FOUR_MASK: u16 = 0x000f;
+
+/// Decodes the opcode passed into an original CHIP-8 Instruction, without executing it
+///	op_code - The two byte value representing an instruction
+pub fn decode(op_code: u16) -> Instruction {
+    decode_for(op_code, InstructionSet::Chip8)
+}
+
+/// Decodes the opcode passed into an Instruction from the instruction set, without executing
+/// it. Opcodes which are not in the instruction set decode as they would on CHIP-8
+///	op_code - The two byte value representing an instruction
+///	set - The instruction set to decode from
+pub fn decode_for(op_code: u16, set: InstructionSet) -> Instruction {
+    use Instruction::*;
+
+    let schip = set.has_super_chip();
+    let xo = set.has_xo_chip();
+    let hires = set.has_hires_chip8();
+    let chip8x = set.has_chip8x();
+    let mega = set.has_mega_chip();
+    let chip8e = set.has_chip8e();
+    let chip8i = set.has_chip8i();
+    match get_nibbles(op_code) {
+        [0, 0, 0xe, 0] => Cls,
+        [0, 0, 0xe, 0xe] => Ret,
+        [0, 2, 3, 0] if hires => Cls,
+        [0, 2, 0xa, 0] if chip8x => NextBackground,
+        [0, 0, 0xe, 0xd] if chip8e => Exit,
+        [0, 1, 5, 1] if chip8e => WaitDt,
+        [0, 1, 8, 8] if chip8e => Skip,
+        [0, 0, 1, 0] if mega => MegaOff,
+        [0, 0, 1, 1] if mega => MegaOn,
+        [0, 0, 0xb, n] if mega => ScuNibble { n },
+        [0, 7, 0, 0] if mega => StopSnd,
+        [0, 6, 0, n] if mega => DigiSndNibble { n },
+        [0, 8, 0, n] if mega => BlendNibble { n },
+        [0, 1, n1, n2] if mega => LdILongByte {
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [0, 2, n1, n2] if mega => LdPalByte {
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [0, 3, n1, n2] if mega => SprWidthByte {
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [0, 4, n1, n2] if mega => SprHeightByte {
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [0, 5, n1, n2] if mega => AlphaByte {
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [0, 9, n1, n2] if mega => CcolByte {
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [0, 0, 0xc, n] if schip => ScdNibble { n },
+        [0, 0, 0xd, n] if xo => ScuNibble { n },
+        [0, 0, 0xf, 0xb] if schip => Scr,
+        [0, 0, 0xf, 0xc] if schip => Scl,
+        [0, 0, 0xf, 0xd] if schip => Exit,
+        [0, 0, 0xf, 0xe] if schip => Low,
+        [0, 0, 0xf, 0xf] if schip => High,
+        [0, n1, n2, n3] => Sys {
+            nnn: nibbles_to_u16(n1, n2, n3),
+        },
+        [1, n1, n2, n3] => JpAddr {
+            nnn: nibbles_to_u16(n1, n2, n3),
+        },
+        [2, n1, n2, n3] => CallAddr {
+            nnn: nibbles_to_u16(n1, n2, n3),
+        },
+        [3, x, n1, n2] => SeVxByte {
+            x,
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [4, x, n1, n2] => SneVxByte {
+            x,
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [5, x, y, 0] => SeVxVy { x, y },
+        [5, x, y, 1] if chip8x => AddNibblesVxVy { x, y },
+        [5, x, y, 1] if chip8e => SgtVxVy { x, y },
+        [5, x, y, 2] if xo || chip8e => LdIVxVy { x, y },
+        [5, x, y, 3] if xo || chip8e => LdVxVyI { x, y },
+        [6, x, n1, n2] => LdVxByte {
+            x,
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [7, x, n1, n2] => AddVxByte {
+            x,
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [8, x, y, 0] => LdVxVy { x, y },
+        [8, x, y, 1] => OrVxVy { x, y },
+        [8, x, y, 2] => AndVxVy { x, y },
+        [8, x, y, 3] => XorVxVy { x, y },
+        [8, x, y, 4] => AddVxVy { x, y },
+        [8, x, y, 5] => SubVxVy { x, y },
+        [8, x, y, 6] => ShrVxVy { x, y },
+        [8, x, y, 7] => SubnVxVy { x, y },
+        [8, x, y, 0xe] => ShlVxVy { x, y },
+        [9, x, y, 0] => SneVxVy { x, y },
+        [0xa, n1, n2, n3] => LdIAddr {
+            nnn: nibbles_to_u16(n1, n2, n3),
+        },
+        [0xb, x, y, n] if chip8x => ColVxVyNibble { x, y, n },
+        [0xb, 0xb, n1, n2] if chip8e => JpBackByte {
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [0xb, 0xf, n1, n2] if chip8e => JpFwdByte {
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [0xb, n1, n2, n3] => JpV0Addr {
+            nnn: nibbles_to_u16(n1, n2, n3),
+        },
+        [0xc, x, n1, n2] => RndVxByte {
+            x,
+            nn: nibbles_to_u8(n1, n2),
+        },
+        [0xd, x, y, n] => DrwVxVyNibble { x, y, n },
+        [0xe, x, 9, 0xe] => SkpVx { x },
+        [0xe, x, 0xa, 1] => SknpVx { x },
+        [0xe, x, 0xf, 2] if chip8x => Skp2Vx { x },
+        [0xe, x, 0xf, 5] if chip8x => Sknp2Vx { x },
+        [0xf, x, 0, 7] => LdVxDt { x },
+        [0xf, x, 0, 0xa] => LdVxK { x },
+        [0xf, x, 1, 5] => LdDtVx { x },
+        [0xf, x, 1, 8] => LdStVx { x },
+        [0xf, x, 1, 0xe] => AddIVx { x },
+        [0xf, x, 2, 9] => LdFVx { x },
+        [0xf, x, 3, 3] => LdBVx { x },
+        [0xf, x, 5, 5] => LdIVx { x },
+        [0xf, x, 6, 5] => LdVxI { x },
+        [0xf, 0, 0, 0] if xo => LdILong,
+        [0xf, x, 0, 3] if chip8e => OutVx { x },
+        [0xf, x, 0xe, 7] if chip8e => InVx { x },
+        [0xf, x, 0xe, 3] if chip8e => InWaitVx { x },
+        [0xf, x, 1, 0xb] if chip8e => SkipVx { x },
+        [0xf, x, 4, 0xf] if chip8e => LdDtVxWait { x },
+        [0xf, x, 0xf, 8] if chip8i => OutVx { x },
+        [0xf, x, 0xf, 0xb] if chip8i => InWaitVx { x },
+        [0xf, n, 0, 1] if xo => Plane { n },
+        [0xf, 0, 0, 2] if xo => Audio,
+        [0xf, x, 3, 0xa] if xo => PitchVx { x },
+        [0xf, x, 3, 0] if schip => LdHfVx { x },
+        [0xf, x, 7, 5] if schip => LdRVx { x },
+        [0xf, x, 8, 5] if schip => LdVxR { x },
+        _ => Unknown(op_code),
+    }
+}
+
+/// Returns the Nibbles (half a byte each) from a 2 byte number
+///	op_code - The two byte value representing an instruction
+fn get_nibbles(op_code: u16) -> Nibbles {
+    [
+        ((op_code & NIBBLE_ONE_MASK) >> 12).try_into().unwrap(),
+        ((op_code & NIBBLE_TWO_MASK) >> 8).try_into().unwrap(),
+        ((op_code & NIBBLE_THREE_MASK) >> 4).try_into().unwrap(),
+        (op_code & NIBBLE_FOUR_MASK).try_into().unwrap(),
+    ]
+}
+
+fn nibbles_to_u8(n1: u8, n2: u8) -> u8 {
+    assert!(n1.leading_zeros() >= 4);
+    assert!(n2.leading_zeros() >= 4);
+    n1 << 4 | n2
+}
+
+fn nibbles_to_u16(n1: u8, n2: u8, n3: u8) -> u16 {
+    assert!(n1.leading_zeros() >= 4);
+    assert!(n2.leading_zeros() >= 4);
+    assert!(n3.leading_zeros() >= 4);
+    (n1 as u16) << 8 | (n2 as u16) << 4 | (n3 as u16)
+}
+
+/// Writes the instruction in the usual assembly mnemonics, e.g. "LD V3, 0x42"
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Instruction::*;
+
+        match *self {
+            Sys { nnn } => write!(f, "SYS {nnn:#05x}"),
+            MegaOff => write!(f, "MEGAOFF"),
+            MegaOn => write!(f, "MEGAON"),
+            LdILongByte { nn } => write!(f, "LDHI {nn:#04x}"),
+            LdPalByte { nn } => write!(f, "LDPAL {nn}"),
+            SprWidthByte { nn } => write!(f, "SPRW {nn}"),
+            SprHeightByte { nn } => write!(f, "SPRH {nn}"),
+            AlphaByte { nn } => write!(f, "ALPHA {nn:#04x}"),
+            DigiSndNibble { n } => write!(f, "DIGISND {n}"),
+            StopSnd => write!(f, "STOPSND"),
+            BlendNibble { n } => write!(f, "BMODE {n}"),
+            CcolByte { nn } => write!(f, "CCOL {nn:#04x}"),
+            WaitDt => write!(f, "WAIT DT"),
+            Skip => write!(f, "SKIP"),
+            NextBackground => write!(f, "BCOL"),
+            Cls => write!(f, "CLS"),
+            Ret => write!(f, "RET"),
+            ScdNibble { n } => write!(f, "SCD {n}"),
+            ScuNibble { n } => write!(f, "SCU {n}"),
+            Scr => write!(f, "SCR"),
+            Scl => write!(f, "SCL"),
+            Exit => write!(f, "EXIT"),
+            Low => write!(f, "LOW"),
+            High => write!(f, "HIGH"),
+            JpAddr { nnn } => write!(f, "JP {nnn:#05x}"),
+            CallAddr { nnn } => write!(f, "CALL {nnn:#05x}"),
+            SeVxByte { x, nn } => write!(f, "SE V{x:X}, {nn:#04x}"),
+            SneVxByte { x, nn } => write!(f, "SNE V{x:X}, {nn:#04x}"),
+            SeVxVy { x, y } => write!(f, "SE V{x:X}, V{y:X}"),
+            SgtVxVy { x, y } => write!(f, "SGT V{x:X}, V{y:X}"),
+            AddNibblesVxVy { x, y } => write!(f, "ADDN V{x:X}, V{y:X}"),
+            LdIVxVy { x, y } => write!(f, "LD [I], V{x:X}-V{y:X}"),
+            LdVxVyI { x, y } => write!(f, "LD V{x:X}-V{y:X}, [I]"),
+            LdVxByte { x, nn } => write!(f, "LD V{x:X}, {nn:#04x}"),
+            AddVxByte { x, nn } => write!(f, "ADD V{x:X}, {nn:#04x}"),
+            LdVxVy { x, y } => write!(f, "LD V{x:X}, V{y:X}"),
+            OrVxVy { x, y } => write!(f, "OR V{x:X}, V{y:X}"),
+            AndVxVy { x, y } => write!(f, "AND V{x:X}, V{y:X}"),
+            XorVxVy { x, y } => write!(f, "XOR V{x:X}, V{y:X}"),
+            AddVxVy { x, y } => write!(f, "ADD V{x:X}, V{y:X}"),
+            SubVxVy { x, y } => write!(f, "SUB V{x:X}, V{y:X}"),
+            ShrVxVy { x, y } => write!(f, "SHR V{x:X}, V{y:X}"),
+            SubnVxVy { x, y } => write!(f, "SUBN V{x:X}, V{y:X}"),
+            ShlVxVy { x, y } => write!(f, "SHL V{x:X}, V{y:X}"),
+            SneVxVy { x, y } => write!(f, "SNE V{x:X}, V{y:X}"),
+            LdIAddr { nnn } => write!(f, "LD I, {nnn:#05x}"),
+            JpV0Addr { nnn } => write!(f, "JP V0, {nnn:#05x}"),
+            JpBackByte { nn } => write!(f, "JPB {nn:#04x}"),
+            JpFwdByte { nn } => write!(f, "JPF {nn:#04x}"),
+            ColVxVyNibble { x, y, n } => write!(f, "COL V{x:X}, V{y:X}, {n}"),
+            RndVxByte { x, nn } => write!(f, "RND V{x:X}, {nn:#04x}"),
+            DrwVxVyNibble { x, y, n } => write!(f, "DRW V{x:X}, V{y:X}, {n}"),
+            SkpVx { x } => write!(f, "SKP V{x:X}"),
+            SknpVx { x } => write!(f, "SKNP V{x:X}"),
+            Skp2Vx { x } => write!(f, "SKP2 V{x:X}"),
+            Sknp2Vx { x } => write!(f, "SKNP2 V{x:X}"),
+            OutVx { x } => write!(f, "OUT V{x:X}"),
+            InVx { x } => write!(f, "IN V{x:X}"),
+            InWaitVx { x } => write!(f, "INW V{x:X}"),
+            SkipVx { x } => write!(f, "SKIP V{x:X}"),
+            LdDtVxWait { x } => write!(f, "LDW DT, V{x:X}"),
+            LdILong => write!(f, "LD I, LONG"),
+            Plane { n } => write!(f, "PLANE {n}"),
+            Audio => write!(f, "AUDIO"),
+            PitchVx { x } => write!(f, "PITCH V{x:X}"),
+            LdVxDt { x } => write!(f, "LD V{x:X}, DT"),
+            LdVxK { x } => write!(f, "LD V{x:X}, K"),
+            LdDtVx { x } => write!(f, "LD DT, V{x:X}"),
+            LdStVx { x } => write!(f, "LD ST, V{x:X}"),
+            AddIVx { x } => write!(f, "ADD I, V{x:X}"),
+            LdFVx { x } => write!(f, "LD F, V{x:X}"),
+            LdHfVx { x } => write!(f, "LD HF, V{x:X}"),
+            LdBVx { x } => write!(f, "LD B, V{x:X}"),
+            LdIVx { x } => write!(f, "LD [I], V{x:X}"),
+            LdVxI { x } => write!(f, "LD V{x:X}, [I]"),
+            LdRVx { x } => write!(f, "LD R, V{x:X}"),
+            LdVxR { x } => write!(f, "LD V{x:X}, R"),
+            Unknown(op_code) => write!(f, "DW {op_code:#06x}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Instruction::*;
+
+    #[test]
+    fn decodes_operands() {
+        assert_eq!(decode(0x00e0), Cls);
+        assert_eq!(decode(0x00ee), Ret);
+        assert_eq!(decode(0x0123), Sys { nnn: 0x123 });
+        assert_eq!(decode(0x1abc), JpAddr { nnn: 0xabc });
+        assert_eq!(decode(0x2def), CallAddr { nnn: 0xdef });
+        assert_eq!(decode(0x3a42), SeVxByte { x: 0xa, nn: 0x42 });
+        assert_eq!(decode(0x5120), SeVxVy { x: 1, y: 2 });
+        assert_eq!(decode(0x8ab6), ShrVxVy { x: 0xa, y: 0xb });
+        assert_eq!(decode(0x8abe), ShlVxVy { x: 0xa, y: 0xb });
+        assert_eq!(decode(0xb300), JpV0Addr { nnn: 0x300 });
+        assert_eq!(decode(0xd125), DrwVxVyNibble { x: 1, y: 2, n: 5 });
+        assert_eq!(decode(0xe59e), SkpVx { x: 5 });
+        assert_eq!(decode(0xf765), LdVxI { x: 7 });
+    }
+
+    #[test]
+    fn decodes_unknown_opcodes() {
+        assert_eq!(decode(0x5121), Unknown(0x5121));
+        assert_eq!(decode(0x8128), Unknown(0x8128));
+        assert_eq!(decode(0xe1ff), Unknown(0xe1ff));
+        assert_eq!(decode(0xf1ff), Unknown(0xf1ff));
+    }
+
+    #[test]
+    fn decodes_super_chip_only_when_selected() {
+        let schip = InstructionSet::SuperChip;
+        assert_eq!(decode(0x00c4), Sys { nnn: 0x0c4 });
+        assert_eq!(decode_for(0x00c4, schip), ScdNibble { n: 4 });
+        assert_eq!(decode_for(0x00fb, schip), Scr);
+        assert_eq!(decode_for(0x00fc, schip), Scl);
+        assert_eq!(decode_for(0x00fd, schip), Exit);
+        assert_eq!(decode_for(0x00fe, schip), Low);
+        assert_eq!(decode_for(0x00ff, schip), High);
+        assert_eq!(decode(0xf130), Unknown(0xf130));
+        assert_eq!(decode_for(0xf130, schip), LdHfVx { x: 1 });
+        assert_eq!(decode_for(0xf775, schip), LdRVx { x: 7 });
+        assert_eq!(decode_for(0xf785, schip), LdVxR { x: 7 });
+    }
+
+    #[test]
+    fn decodes_xo_chip_only_when_selected() {
+        let xo = InstructionSet::XoChip;
+        assert_eq!(
+            decode_for(0x00d3, InstructionSet::SuperChip),
+            Sys { nnn: 0x0d3 }
+        );
+        assert_eq!(decode_for(0x00d3, xo), ScuNibble { n: 3 });
+        assert_eq!(decode_for(0x00c4, xo), ScdNibble { n: 4 });
+        assert_eq!(decode(0x5122), Unknown(0x5122));
+        assert_eq!(decode_for(0x5122, xo), LdIVxVy { x: 1, y: 2 });
+        assert_eq!(decode_for(0x5213, xo), LdVxVyI { x: 2, y: 1 });
+        assert_eq!(decode(0xf000), Unknown(0xf000));
+        assert_eq!(decode_for(0xf000, xo), LdILong);
+        assert_eq!(decode_for(0xf201, xo), Plane { n: 2 });
+        assert_eq!(decode(0xf002), Unknown(0xf002));
+        assert_eq!(decode_for(0xf002, xo), Audio);
+        assert_eq!(decode_for(0xf33a, xo), PitchVx { x: 3 });
+        assert_eq!(decode_for(0xf130, xo), LdHfVx { x: 1 });
+    }
+
+    #[test]
+    fn decodes_hires_erase_only_when_selected() {
+        assert_eq!(decode(0x0230), Sys { nnn: 0x230 });
+        assert_eq!(decode_for(0x0230, InstructionSet::HiresChip8), Cls);
+        assert_eq!(decode_for(0x00e0, InstructionSet::HiresChip8), Cls);
+    }
+
+    #[test]
+    fn decodes_chip8x_only_when_selected() {
+        let chip8x = InstructionSet::Chip8X;
+        assert_eq!(decode(0x02a0), Sys { nnn: 0x2a0 });
+        assert_eq!(decode_for(0x02a0, chip8x), NextBackground);
+        assert_eq!(decode_for(0x5121, chip8x), AddNibblesVxVy { x: 1, y: 2 });
+        assert_eq!(decode(0xb123), JpV0Addr { nnn: 0x123 });
+        assert_eq!(
+            decode_for(0xb123, chip8x),
+            ColVxVyNibble { x: 1, y: 2, n: 3 }
+        );
+        assert_eq!(decode_for(0xe3f2, chip8x), Skp2Vx { x: 3 });
+        assert_eq!(decode_for(0xe3f5, chip8x), Sknp2Vx { x: 3 });
+    }
+
+    #[test]
+    fn decodes_mega_chip_only_when_selected() {
+        let mega = InstructionSet::MegaChip;
+        assert_eq!(decode(0x0011), Sys { nnn: 0x011 });
+        assert_eq!(decode_for(0x0010, mega), MegaOff);
+        assert_eq!(decode_for(0x0011, mega), MegaOn);
+        assert_eq!(decode_for(0x0112, mega), LdILongByte { nn: 0x12 });
+        assert_eq!(decode_for(0x02ff, mega), LdPalByte { nn: 0xff });
+        assert_eq!(decode_for(0x0310, mega), SprWidthByte { nn: 0x10 });
+        assert_eq!(decode_for(0x0420, mega), SprHeightByte { nn: 0x20 });
+        assert_eq!(decode_for(0x0580, mega), AlphaByte { nn: 0x80 });
+        assert_eq!(decode_for(0x0601, mega), DigiSndNibble { n: 1 });
+        assert_eq!(decode_for(0x0700, mega), StopSnd);
+        assert_eq!(decode_for(0x0803, mega), BlendNibble { n: 3 });
+        assert_eq!(decode_for(0x0905, mega), CcolByte { nn: 5 });
+        assert_eq!(decode_for(0x00b2, mega), ScuNibble { n: 2 });
+        assert_eq!(decode_for(0x00fb, mega), Scr);
+    }
+
+    #[test]
+    fn decodes_chip8e_and_chip8i_only_when_selected() {
+        let chip8e = InstructionSet::Chip8E;
+        let chip8i = InstructionSet::Chip8I;
+        assert_eq!(decode_for(0x00ed, chip8e), Exit);
+        assert_eq!(decode_for(0x0151, chip8e), WaitDt);
+        assert_eq!(decode_for(0x0188, chip8e), Skip);
+        assert_eq!(decode_for(0x5121, chip8e), SgtVxVy { x: 1, y: 2 });
+        assert_eq!(decode_for(0x5122, chip8e), LdIVxVy { x: 1, y: 2 });
+        assert_eq!(decode_for(0xbb10, chip8e), JpBackByte { nn: 0x10 });
+        assert_eq!(decode_for(0xbf10, chip8e), JpFwdByte { nn: 0x10 });
+        assert_eq!(decode_for(0xb310, chip8e), JpV0Addr { nnn: 0x310 });
+        assert_eq!(decode_for(0xf303, chip8e), OutVx { x: 3 });
+        assert_eq!(decode_for(0xf3e7, chip8e), InVx { x: 3 });
+        assert_eq!(decode_for(0xf31b, chip8e), SkipVx { x: 3 });
+        assert_eq!(decode_for(0xf34f, chip8e), LdDtVxWait { x: 3 });
+        assert_eq!(decode(0xf3f8), Unknown(0xf3f8));
+        assert_eq!(decode_for(0xf3f8, chip8i), OutVx { x: 3 });
+        assert_eq!(decode_for(0xf3fb, chip8i), InWaitVx { x: 3 });
+        assert_eq!(decode_for(0xf303, chip8i), Unknown(0xf303));
+    }
+
+    #[test]
+    fn displays_mnemonics() {
+        assert_eq!(decode(0x6a42).to_string(), "LD VA, 0x42");
+        assert_eq!(decode(0xa2f0).to_string(), "LD I, 0x2f0");
+        assert_eq!(decode(0xd01f).to_string(), "DRW V0, V1, 15");
+        assert_eq!(decode(0xf355).to_string(), "LD [I], V3");
+        assert_eq!(decode(0xffff).to_string(), "DW 0xffff");
+    }
+}
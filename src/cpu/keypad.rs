@@ -0,0 +1,51 @@
+// The number of keys on the hex keypad, 0 .. F
+pub const NUM_KEYS: usize = 16;
+
+// Emulates the 16-key hex keypad
+#[derive(Default)]
+pub struct Keypad {
+    // Represents whether each key 0 .. F is currently held down
+    pressed: [bool; NUM_KEYS],
+    // The last key which was pressed and then released, if nobody has taken it yet
+    released: Option<u8>,
+}
+
+impl Keypad {
+    /// Returns a new Keypad with no keys held down
+    pub fn new() -> Keypad {
+        Keypad::default()
+    }
+
+    /// Marks the key as held down
+    ///	key - The key to press, only the lowest nibble is used
+    pub fn press(&mut self, key: u8) {
+        self.pressed[(key & 0xf) as usize] = true;
+    }
+
+    /// Marks the key as no longer held down. If the key was held down it is remembered as
+    /// released, so a waiting instruction can take it
+    ///	key - The key to release, only the lowest nibble is used
+    pub fn release(&mut self, key: u8) {
+        let key = key & 0xf;
+        if self.pressed[key as usize] {
+            self.pressed[key as usize] = false;
+            self.released = Some(key);
+        }
+    }
+
+    /// Returns true if the key is currently held down
+    ///	key - The key to check, only the lowest nibble is used
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.pressed[(key & 0xf) as usize]
+    }
+
+    /// Forgets any key which was released before now
+    pub fn clear_released(&mut self) {
+        self.released = None;
+    }
+
+    /// Returns the last key which was pressed and then released, if any, and forgets it
+    pub fn take_released(&mut self) -> Option<u8> {
+        self.released.take()
+    }
+}
@@ -0,0 +1,340 @@
+// The size of the MegaChip screen
+pub const MEGA_GRAPHICS_COLUMNS: usize = 256;
+pub const MEGA_GRAPHICS_ROWS: usize = 192;
+
+// The number of colours in the MegaChip palette, colour 0 is always transparent
+pub const NUM_MEGA_COLOURS: usize = 256;
+
+// How a MegaChip sprite is mixed with the pixels already on the screen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    // The sprite replaces the screen
+    #[default]
+    Normal,
+    // The sprite is mixed in at 25%
+    Quarter,
+    // The sprite is mixed in at 50%
+    Half,
+    // The sprite is mixed in at 75%
+    ThreeQuarters,
+    // The sprite is added to the screen
+    Add,
+    // The sprite is multiplied with the screen
+    Multiply,
+}
+
+impl BlendMode {
+    /// Returns the blend mode picked by 080n, or None if n is not a blend mode
+    ///	n - The blend mode number, 0 .. 5
+    pub fn from_nibble(n: u8) -> Option<BlendMode> {
+        match n {
+            0 => Some(BlendMode::Normal),
+            1 => Some(BlendMode::Quarter),
+            2 => Some(BlendMode::Half),
+            3 => Some(BlendMode::ThreeQuarters),
+            4 => Some(BlendMode::Add),
+            5 => Some(BlendMode::Multiply),
+            _ => None,
+        }
+    }
+
+    /// Returns the RGBA colour of the sprite colour mixed onto the screen colour
+    ///	sprite - The colour being drawn
+    ///	screen - The colour already on the screen
+    pub fn blend(self, sprite: [u8; 4], screen: [u8; 4]) -> [u8; 4] {
+        let mix = |quarters: u16| {
+            let channel = |i: usize| {
+                ((sprite[i] as u16 * quarters + screen[i] as u16 * (4 - quarters)) / 4) as u8
+            };
+            [channel(0), channel(1), channel(2), 0xff]
+        };
+        match self {
+            BlendMode::Normal => sprite,
+            BlendMode::Quarter => mix(1),
+            BlendMode::Half => mix(2),
+            BlendMode::ThreeQuarters => mix(3),
+            BlendMode::Add => {
+                let channel = |i: usize| sprite[i].saturating_add(screen[i]);
+                [channel(0), channel(1), channel(2), 0xff]
+            }
+            BlendMode::Multiply => {
+                let channel = |i: usize| (sprite[i] as u16 * screen[i] as u16 / 0xff) as u8;
+                [channel(0), channel(1), channel(2), 0xff]
+            }
+        }
+    }
+}
+
+// A digitized sound started by 060n, its 8-bit unsigned samples are in memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DigitizedSound {
+    // The address of the first sample
+    pub start: usize,
+    // The number of samples
+    pub len: usize,
+    // The number of samples played each second
+    pub sample_rate: u16,
+    // Set if the sound starts over once it has finished
+    pub looping: bool,
+}
+
+// The MegaChip screen, which lives alongside the CHIP-8 graphics and takes over from them while
+// MegaChip mode is on. Sprites are drawn into a back buffer of palette colours, 00E0 shows it and
+// starts a new one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MegaChip {
+    // Set while MegaChip mode is on, between 0011 and 0010
+    enabled: bool,
+    // The colours loaded by 02nn, as RGBA
+    palette: [[u8; 4]; NUM_MEGA_COLOURS],
+    // The number of colours 02nn has loaded, from colour 1 on. The colours after them are still
+    // the ones set by set_base_colours
+    loaded_colours: usize,
+    // The number of pixels across each sprite
+    sprite_width: usize,
+    // The number of rows in each sprite
+    sprite_height: usize,
+    // How sprites are mixed onto the screen
+    blend_mode: BlendMode,
+    // The palette colour sprites collide with
+    collision_colour: u8,
+    // The opacity of the whole screen, 0 is black and 255 is fully shown
+    alpha: u8,
+    // The palette colour last drawn at each pixel, row by row
+    indices: Vec<u8>,
+    // The RGBA colour of each pixel being drawn, row by row
+    back: Vec<[u8; 4]>,
+    // The RGBA colour of each pixel being shown, row by row
+    front: Vec<[u8; 4]>,
+    // The digitized sound playing, if any
+    sound: Option<DigitizedSound>,
+    // The number of sounds 060n has started, so a sound started again can be told apart from the
+    // same sound still playing
+    sounds_started: u32,
+}
+
+impl Default for MegaChip {
+    fn default() -> MegaChip {
+        MegaChip::new()
+    }
+}
+
+impl MegaChip {
+    /// Returns a new MegaChip screen, with MegaChip mode off and a black palette
+    pub fn new() -> MegaChip {
+        let pixels = MEGA_GRAPHICS_COLUMNS * MEGA_GRAPHICS_ROWS;
+        MegaChip {
+            enabled: false,
+            palette: [[0, 0, 0, 0xff]; NUM_MEGA_COLOURS],
+            loaded_colours: 0,
+            sprite_width: 0,
+            sprite_height: 0,
+            blend_mode: BlendMode::default(),
+            collision_colour: 0,
+            alpha: 0xff,
+            indices: vec![0; pixels],
+            back: vec![[0, 0, 0, 0xff]; pixels],
+            front: vec![[0, 0, 0, 0xff]; pixels],
+            sound: None,
+            sounds_started: 0,
+        }
+    }
+
+    /// Returns true while MegaChip mode is on
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turns MegaChip mode on or off
+    ///	enabled - Whether MegaChip mode should be on
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Sets palette colours 1 .. colours.len() from ARGB bytes, four per colour
+    ///	argb - The colours, as alpha, red, green and blue bytes
+    pub fn load_palette(&mut self, argb: &[u8]) {
+        for (colour, bytes) in self.palette[1..].iter_mut().zip(argb.chunks_exact(4)) {
+            *colour = [bytes[1], bytes[2], bytes[3], bytes[0]];
+        }
+        let count = (argb.len() / 4).min(NUM_MEGA_COLOURS - 1);
+        self.loaded_colours = self.loaded_colours.max(count);
+    }
+
+    /// Sets the palette colours 02nn has not loaded yet from the first of colours, which stand in
+    /// for them until a rom loads its own. Colour 0 stays transparent
+    ///	colours - The RGBA colours, starting with colour 0
+    pub fn set_base_colours(&mut self, colours: &[[u8; 4]]) {
+        for (i, &colour) in colours.iter().enumerate().skip(self.loaded_colours + 1) {
+            self.palette[i] = colour;
+        }
+    }
+
+    /// Returns the RGBA palette
+    pub fn palette(&self) -> &[[u8; 4]; NUM_MEGA_COLOURS] {
+        &self.palette
+    }
+
+    /// Sets the size of the sprites drawn by Dxyn, 0 meaning 256 for either
+    ///	width - The number of pixels across each sprite
+    ///	height - The number of rows in each sprite
+    pub fn set_sprite_size(&mut self, width: Option<u8>, height: Option<u8>) {
+        let size = |n: u8| if n == 0 { 256 } else { n as usize };
+        if let Some(width) = width {
+            self.sprite_width = size(width);
+        }
+        if let Some(height) = height {
+            self.sprite_height = size(height);
+        }
+    }
+
+    /// Returns the (width, height) of the sprites drawn by Dxyn
+    pub fn sprite_size(&self) -> (usize, usize) {
+        (self.sprite_width, self.sprite_height)
+    }
+
+    /// Sets how sprites are mixed onto the screen
+    ///	blend_mode - The new blend mode
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    /// Sets the palette colour sprites collide with
+    ///	colour - The palette colour
+    pub fn set_collision_colour(&mut self, colour: u8) {
+        self.collision_colour = colour;
+    }
+
+    /// Sets the opacity of the whole screen
+    ///	alpha - 0 for black, 255 for fully shown
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+    }
+
+    /// Draws a sprite of palette colours into the back buffer with its top left corner at
+    /// (x, y), clipping it at the edges of the screen. Colour 0 is transparent. Returns true if
+    /// any pixel drawn over had the collision colour
+    ///	x - The column of the top left corner
+    ///	y - The row of the top left corner
+    ///	sprite - The palette colours, row by row, sprite_width in each row
+    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8]) -> bool {
+        let mut collision = false;
+        for (row, colours) in sprite.chunks(self.sprite_width.max(1)).enumerate() {
+            let cur_y = y + row;
+            if cur_y >= MEGA_GRAPHICS_ROWS {
+                break;
+            }
+            for (column, &colour) in colours.iter().enumerate() {
+                let cur_x = x + column;
+                if cur_x >= MEGA_GRAPHICS_COLUMNS {
+                    break;
+                }
+                if colour == 0 {
+                    continue;
+                }
+                let i = cur_y * MEGA_GRAPHICS_COLUMNS + cur_x;
+                collision |= self.indices[i] == self.collision_colour;
+                self.indices[i] = colour;
+                let rgba = self.palette[colour as usize];
+                self.back[i] = self.blend_mode.blend(rgba, self.back[i]);
+            }
+        }
+        collision
+    }
+
+    /// Shows the back buffer, then clears it for the next frame
+    pub fn present(&mut self) {
+        std::mem::swap(&mut self.front, &mut self.back);
+        self.back.fill([0, 0, 0, 0xff]);
+        self.indices.fill(0);
+    }
+
+    /// Returns the RGBA colour of each pixel being shown, row by row, faded by the screen alpha
+    pub fn frame(&self) -> impl Iterator<Item = [u8; 4]> + '_ {
+        let fade = |channel: u8| (channel as u16 * self.alpha as u16 / 0xff) as u8;
+        self.front
+            .iter()
+            .map(move |&[r, g, b, _]| [fade(r), fade(g), fade(b), 0xff])
+    }
+
+    /// Returns the digitized sound playing, if any
+    pub fn sound(&self) -> Option<DigitizedSound> {
+        self.sound
+    }
+
+    /// Returns the number of sounds started so far, which changes every time 060n starts one,
+    /// even if it is the sound already playing
+    pub fn sounds_started(&self) -> u32 {
+        self.sounds_started
+    }
+
+    /// Starts or stops the digitized sound. A sound started plays from its beginning, even if it
+    /// is the sound already playing
+    ///	sound - The sound to play, None to stop
+    pub fn set_sound(&mut self, sound: Option<DigitizedSound>) {
+        if sound.is_some() {
+            self.sounds_started = self.sounds_started.wrapping_add(1);
+        }
+        self.sound = sound;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palette_is_loaded_from_colour_1() {
+        let mut mega = MegaChip::new();
+        mega.load_palette(&[0xff, 0x10, 0x20, 0x30, 0x80, 0x40, 0x50, 0x60]);
+        assert_eq!(mega.palette()[0], [0, 0, 0, 0xff]);
+        assert_eq!(mega.palette()[1], [0x10, 0x20, 0x30, 0xff]);
+        assert_eq!(mega.palette()[2], [0x40, 0x50, 0x60, 0x80]);
+    }
+
+    #[test]
+    fn base_colours_fill_the_colours_not_loaded() {
+        let mut mega = MegaChip::new();
+        mega.load_palette(&[0xff, 0x10, 0x20, 0x30]);
+        mega.set_base_colours(&[[0x99, 0x99, 0x99, 0xff]; 4]);
+        assert_eq!(mega.palette()[0], [0, 0, 0, 0xff]);
+        assert_eq!(mega.palette()[1], [0x10, 0x20, 0x30, 0xff]);
+        assert_eq!(mega.palette()[3], [0x99, 0x99, 0x99, 0xff]);
+        assert_eq!(mega.palette()[4], [0, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn blend_modes_mix_colours() {
+        let (sprite, screen) = ([0xff, 0x80, 0x00, 0xff], [0x00, 0x80, 0xff, 0xff]);
+        assert_eq!(BlendMode::Normal.blend(sprite, screen), sprite);
+        assert_eq!(
+            BlendMode::Half.blend(sprite, screen),
+            [0x7f, 0x80, 0x7f, 0xff]
+        );
+        assert_eq!(
+            BlendMode::Add.blend(sprite, screen),
+            [0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(
+            BlendMode::Multiply.blend(sprite, screen),
+            [0, 0x40, 0, 0xff]
+        );
+        assert_eq!(BlendMode::from_nibble(6), None);
+    }
+
+    #[test]
+    fn sprites_collide_with_the_collision_colour() {
+        let mut mega = MegaChip::new();
+        mega.load_palette(&[0xff, 0xff, 0xff, 0xff]);
+        mega.set_sprite_size(Some(2), Some(1));
+        mega.set_collision_colour(1);
+        assert!(!mega.draw_sprite(255, 0, &[1, 1]));
+        assert!(mega.draw_sprite(254, 0, &[0, 1]));
+        assert!(!mega.draw_sprite(0, 0, &[0, 0]));
+
+        mega.present();
+        let frame: Vec<[u8; 4]> = mega.frame().collect();
+        assert_eq!(frame[255], [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(frame[254], [0, 0, 0, 0xff]);
+    }
+}
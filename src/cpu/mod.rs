@@ -1,124 +1,1015 @@
+mod colour;
+mod config;
+mod error;
+mod font;
+mod graphics;
+mod instruction;
+mod keypad;
+mod megachip;
 mod ops;
+mod platform;
+mod quirks;
+mod timers;
+mod timing;
 
-use crate::cpu::ops::exec_opcode;
-use pixels::Pixels;
-use std::fs;
-use std::path::PathBuf;
-use std::u8;
+pub use crate::cpu::colour::{ColourMap, VP590_BACKGROUNDS, VP590_FOREGROUNDS};
+pub use crate::cpu::config::CpuConfig;
+pub use crate::cpu::error::{CpuError, FaultPolicy, FontError, RomError};
+pub use crate::cpu::font::{
+    BigFont, Font, SmallFont, BIG_FONT_BYTES, BIG_FONT_SET_START, FONT_SET_START, SMALL_FONT_BYTES,
+};
+pub use crate::cpu::graphics::{Graphics, ALL_PLANES, DEFAULT_PLANE};
+pub use crate::cpu::instruction::{decode, decode_for, Instruction, InstructionSet};
+pub use crate::cpu::keypad::{Keypad, NUM_KEYS};
+pub use crate::cpu::megachip::{
+    BlendMode, DigitizedSound, MegaChip, MEGA_GRAPHICS_COLUMNS, MEGA_GRAPHICS_ROWS,
+};
+pub use crate::cpu::platform::Platform;
+pub use crate::cpu::quirks::Quirks;
+pub use crate::cpu::timers::{Timers, TIMER_HZ};
+pub use crate::cpu::timing::{vip_cycles, VIP_CYCLES_PER_FRAME, VIP_FRAME_BUDGET};
+
+use crate::cpu::ops::execute;
+use crate::display::{DisplaySink, Frame, Palette};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
 
 // The memory address the program counter starts at
-const START_ADDRESS: u16 = 0x200;
-
-// The starting address in memory where the fonts are stored
-const FONT_SET_START: usize = 0x50;
-
-// The bytes for each given alphanumeric
-const FONT_SET: [u8; 80] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-];
-
-const GRAPHICS_ROWS: usize = 32;
-const GRAPHICS_COLUMNS: usize = 64;
-
-const NUM_REGISTERS: usize = 16;
+pub const START_ADDRESS: u16 = 0x200;
+
+// The size of the usual low resolution screen, platforms can pick another with Cpu::with_platform
+pub const GRAPHICS_ROWS: usize = 32;
+pub const GRAPHICS_COLUMNS: usize = 64;
+// The number of rows on the two-page hires CHIP-8 screen, which is as wide as the usual one
+pub const TWO_PAGE_GRAPHICS_ROWS: usize = 64;
+// Where the program of a two-page hires CHIP-8 rom starts, after its interpreter patch
+const TWO_PAGE_PROGRAM_START: u16 = 0x2c0;
+// The jump two-page hires CHIP-8 roms start with, over their interpreter patch
+const TWO_PAGE_PROLOGUE: [u8; 2] = [0x12, 0x60];
+// The size of the SUPER-CHIP high resolution screen
+pub const HIRES_GRAPHICS_ROWS: usize = 64;
+pub const HIRES_GRAPHICS_COLUMNS: usize = 128;
+
+pub const NUM_REGISTERS: usize = 16;
 // In bytes
 const KILOBYTE: usize = 1024;
-const MEM_SIZE: usize = 4 * KILOBYTE;
-const CALL_STACK_SIZE: usize = 16;
+pub const MEM_SIZE: usize = 4 * KILOBYTE;
+// The memory of XO-CHIP, which can address all of it with F000 nnnn
+pub const XO_MEM_SIZE: usize = 64 * KILOBYTE;
+// The memory of MegaChip, which can address all of it with 01nn nnnn
+pub const MEGA_MEM_SIZE: usize = 16 * KILOBYTE * KILOBYTE;
+// The number of subroutine calls which can be nested, unless the CpuConfig picks another
+pub const CALL_STACK_SIZE: usize = 16;
+// The number of bytes in the XO-CHIP audio pattern, 128 one-bit samples
+pub const AUDIO_PATTERN_BYTES: usize = 16;
+// The pitch the XO-CHIP audio pattern is played at until Fx3A sets another, 4000 samples a second
+pub const DEFAULT_PITCH: u8 = 64;
+// The number of SUPER-CHIP RPL user flags, saved and restored by Fx75 and Fx85
+pub const NUM_RPL_FLAGS: usize = 16;
 
 // Emulates the memory and the cpu
 pub struct Cpu {
     // Represents the registers V0 .. VF
     registers: [u8; NUM_REGISTERS],
-    //  Represents the memory of the system, in bytes
-    memory: [u8; MEM_SIZE],
-    // Register typically used to store adresses
-    index_register: u16,
+    //  Represents the memory of the system, in bytes, sized for the instruction set
+    memory: Vec<u8>,
+    // Register typically used to store adresses, 24 bits wide for MegaChip
+    index_register: u32,
+    // The address roms are loaded at and run from
+    start_address: u16,
+    // The address of the small font, which Fx29 points into
+    font_address: u16,
+    // The address of the big font, which Fx30 points into
+    big_font_address: u16,
     // Represents the program counter
     pc: u16,
-    // Represents the call stack
-    stack: [u16; CALL_STACK_SIZE],
-    // Represents the stack pointer
-    sp: u8,
-    // ??
-    delay_timer: u8,
-    // ??
-    sound_timer: u8,
+    // Represents the call stack, holding the return address of each call
+    stack: Vec<u16>,
+    // The number of subroutine calls which can be nested, None for no limit
+    stack_depth: Option<usize>,
+    // Represents the delay and sound timers, which count down at 60 Hz
+    timers: Timers,
     // Represents the pixels on the screen
-    graphics: [[bool; GRAPHICS_COLUMNS]; GRAPHICS_ROWS],
+    graphics: Graphics,
+    // The (width, height) of the low resolution screen, which 00FE switches back to
+    low_resolution: (usize, usize),
+    // Set while the SUPER-CHIP high resolution screen is on, after 00FF
+    hires: bool,
+    // The instructions the Cpu decodes
+    instruction_set: InstructionSet,
+    // The bit planes which are drawn, cleared and scrolled, picked by the XO-CHIP Fn01
+    planes: u8,
+    // Represents the SUPER-CHIP RPL user flags, which outlive the program on real hardware
+    rpl_flags: [u8; NUM_RPL_FLAGS],
+    // Set once the program has exited with 00FD
+    exited: bool,
+    // Represents the hex keypad
+    keypad: Keypad,
+    // Represents the second hex keypad of CHIP-8X
+    second_keypad: Keypad,
+    // The CHIP-8X colours of the screen, only there when the instruction set is CHIP-8X
+    colour_map: Option<ColourMap>,
+    // The MegaChip screen, only there when the instruction set is MegaChip
+    megachip: Option<MegaChip>,
+    // The register waiting to receive a key, while execution is halted on Fx0A
+    key_wait: Option<usize>,
+    // Set while execution is halted until the delay timer reaches 0, on the CHIP-8E 0151 and Fx4F
+    delay_wait: bool,
+    // The last byte written to the output port by the CHIP-8E and CHIP-8I out instructions
+    output_port: u8,
+    // The byte waiting on the input port, until an in instruction reads it
+    input_port: Option<u8>,
+    // The interpretations of the ambiguous instructions
+    quirks: Quirks,
+    // The XO-CHIP audio pattern loaded by F002, played while the sound timer runs
+    audio_pattern: Option<[u8; AUDIO_PATTERN_BYTES]>,
+    // The pitch the audio pattern is played at, set by Fx3A
+    pitch: u8,
+    // Set while execution is halted after a draw, until the next timer tick
+    vblank_wait: bool,
+    // Set while a Dxyn is held until the next frame starts, with the COSMAC VIP timing model on
+    draw_held: bool,
+    // Set when the screen may have changed since it was last shown
+    screen_dirty: bool,
+    // The colours the screen is shown in, by the planes each pixel is on in
+    palette: Palette,
+    // The 1802 machine cycles left in this frame, while the COSMAC VIP timing model is on
+    cycle_budget: Option<u32>,
+    // What to do when an instruction faults
+    fault_policy: FaultPolicy,
+    // The fault the Cpu is halted on, if any
+    fault: Option<CpuError>,
+}
+
+impl Default for Cpu {
+    fn default() -> Cpu {
+        Cpu::new(CpuConfig::default())
+    }
 }
 
 impl Cpu {
-    /* Returns a new Cpu struct */
-    pub fn new() -> Cpu {
+    /// Returns a new Cpu with the memory, call stack, start address and fonts of the config.
+    /// Memory is never made too small to hold the fonts
+    ///
+    /// `config` - The sizes and addresses to build the Cpu with
+    pub fn new(config: CpuConfig) -> Cpu {
         let mut cpu = Cpu {
             registers: [0; NUM_REGISTERS],
-            memory: [0; MEM_SIZE],
+            memory: vec![0; config.memory_size.max(config.font.end())],
             index_register: 0,
-            pc: START_ADDRESS,
-            stack: [0; CALL_STACK_SIZE],
-            sp: 0,
-            delay_timer: u8::MAX,
-            sound_timer: u8::MAX,
-            graphics: [[false; GRAPHICS_COLUMNS]; GRAPHICS_ROWS],
+            start_address: config.start_address,
+            font_address: config.font.small_address,
+            big_font_address: config.font.big_address,
+            pc: config.start_address,
+            stack: Vec::new(),
+            stack_depth: config.stack_depth,
+            timers: Timers::new(),
+            graphics: Graphics::new(GRAPHICS_COLUMNS, GRAPHICS_ROWS),
+            low_resolution: (GRAPHICS_COLUMNS, GRAPHICS_ROWS),
+            hires: false,
+            instruction_set: InstructionSet::default(),
+            planes: DEFAULT_PLANE,
+            rpl_flags: [0; NUM_RPL_FLAGS],
+            exited: false,
+            keypad: Keypad::new(),
+            second_keypad: Keypad::new(),
+            colour_map: None,
+            megachip: None,
+            key_wait: None,
+            delay_wait: false,
+            output_port: 0,
+            input_port: None,
+            quirks: Quirks::default(),
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            vblank_wait: false,
+            draw_held: false,
+            screen_dirty: true,
+            palette: Palette::default(),
+            cycle_budget: None,
+            fault_policy: FaultPolicy::default(),
+            fault: None,
         };
-        for (i, &data) in FONT_SET.iter().enumerate() {
-            cpu.memory[FONT_SET_START + i] = data;
-        }
+        let small = config.font.small_address as usize;
+        cpu.memory[small..small + SMALL_FONT_BYTES].copy_from_slice(&config.font.small);
+        let big = config.font.big_address as usize;
+        cpu.memory[big..big + BIG_FONT_BYTES].copy_from_slice(&config.font.big);
+        cpu
+    }
+
+    /// Returns a new Cpu set up like the platform, with its instruction set, memory, call
+    /// stack, quirks, start address and screen
+    ///
+    /// `platform` - The interpreter to set the Cpu up like
+    pub fn with_platform(platform: Platform) -> Cpu {
+        Cpu::with_platform_config(platform, platform.config())
+    }
+
+    /// Returns a new Cpu set up like the platform, but built with the config instead of the
+    /// platform's own, e.g. to run it with another font
+    ///
+    /// `platform` - The interpreter to set the Cpu up like
+    /// `config` - The sizes, addresses and fonts to build the Cpu with
+    pub fn with_platform_config(platform: Platform, config: CpuConfig) -> Cpu {
+        let mut cpu = Cpu::new(config);
+        cpu.low_resolution = platform.resolution();
+        cpu.quirks = platform.quirks();
+        cpu.fit_screen(false);
+        cpu.set_instruction_set(platform.instruction_set());
         cpu
     }
 
-    /// Loads the rom stored at puth buf into memory, starting at START_ADDRESS
+    /// Loads the rom stored at path into memory, starting at the start address
     ///
     ///	`self` - The Cpu object to load the rom into
     ///	`path` - The path which we should load the rom from
-    pub fn load_rom(&mut self, path: &PathBuf) {
-        let rom_data = fs::read(path).expect("Something went wrong with the file, try again");
-        for (i, &data) in rom_data.iter().enumerate() {
-            self.memory[START_ADDRESS as usize + i] = data;
+    pub fn load_rom(&mut self, path: &Path) -> Result<(), RomError> {
+        self.load_rom_reader(File::open(path)?)
+    }
+
+    /// Loads the rom read from reader into memory, starting at the start address
+    ///
+    ///	`self` - The Cpu object to load the rom into
+    ///	`reader` - The reader which we should read the whole rom from
+    pub fn load_rom_reader(&mut self, mut reader: impl Read) -> Result<(), RomError> {
+        let mut rom_data = Vec::new();
+        reader.read_to_end(&mut rom_data)?;
+        self.load_rom_bytes(&rom_data)
+    }
+
+    /// Copies the rom into memory, starting at the start address. Two-page hires CHIP-8 roms
+    /// start with a jump over a patch to the VIP interpreter, which is skipped so the program
+    /// runs from right after the patch
+    ///
+    ///	`self` - The Cpu object to load the rom into
+    ///	`rom_data` - The bytes of the rom
+    pub fn load_rom_bytes(&mut self, rom_data: &[u8]) -> Result<(), RomError> {
+        let start = self.start_address as usize;
+        let max = self.memory.len().saturating_sub(start);
+        if rom_data.is_empty() {
+            return Err(RomError::Empty);
+        }
+        if rom_data.len() > max {
+            return Err(RomError::TooLarge {
+                size: rom_data.len(),
+                max,
+            });
         }
+        self.memory[start..start + rom_data.len()].copy_from_slice(rom_data);
+        if self.instruction_set.has_hires_chip8() && rom_data.starts_with(&TWO_PAGE_PROLOGUE) {
+            self.pc = TWO_PAGE_PROGRAM_START;
+        }
+        Ok(())
     }
 
-    /// "Cycles" the Cpu. executing the next instruction and decrementing the proper timers
+    /// "Cycles" the Cpu. fetching the next instruction, moving the program counter past it, then
+    /// executing it. While the Cpu is waiting for a key no instruction is executed. The timers
+    /// are not touched, the host counts them down with tick_timers or update_timers.
+    /// If the instruction faults, the fault policy decides whether the error is returned and
+    /// whether the Cpu halts on it
     ///
     /// `self` - The Cpu object which we should cycle
-    pub fn cycle(&mut self) {
-        let opcode =
-            ((self.memory[self.pc as usize] << 4) + (self.memory[(self.pc + 1) as usize])) as u16;
-        exec_opcode(self, opcode);
+    pub fn cycle(&mut self) -> Result<(), CpuError> {
+        if let Some(error) = self.fault {
+            return Err(error);
+        }
+        match self.step() {
+            Err(error) if self.fault_policy == FaultPolicy::Halt => {
+                self.fault = Some(error);
+                Err(error)
+            }
+            Err(error) if self.fault_policy == FaultPolicy::Report => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    /// Executes the next instruction, or checks for a key if waiting on one. With the COSMAC VIP
+    /// timing model on, nothing is executed once the frame's cycles are spent, and any wait
+    /// spends the rest of them
+    ///
+    /// `self` - The Cpu object which we should step
+    fn step(&mut self) -> Result<(), CpuError> {
+        if self.cycle_budget == Some(0) {
+            return Ok(());
+        }
+        if self.vblank_wait || self.exited {
+            self.end_frame();
+            return Ok(());
+        }
+        if self.is_waiting_for_key() {
+            self.poll_key_wait();
+            self.end_frame();
+            return Ok(());
+        }
+        if self.delay_wait {
+            self.delay_wait = self.timers.delay() != 0;
+            if self.delay_wait {
+                self.end_frame();
+                return Ok(());
+            }
+        }
+        let pc = self.pc;
+        let in_memory = pc as usize + 1 < self.memory.len();
+        let Some(next_pc) = pc.checked_add(2).filter(|_| in_memory) else {
+            self.end_frame();
+            return Err(CpuError::PcOutOfBounds { pc });
+        };
+        let op_code = (self.memory[pc as usize] as u16) << 8 | self.memory[pc as usize + 1] as u16;
+        let instruction = decode_for(op_code, self.instruction_set);
+        // The VIP interpreter waits for the vertical blank before drawing each sprite, so the
+        // draw is held until the next frame starts and runs then
+        if self.has_vip_timing() && matches!(instruction, Instruction::DrwVxVyNibble { .. }) {
+            self.draw_held = !self.draw_held;
+            if self.draw_held {
+                self.end_frame();
+                return Ok(());
+            }
+        }
+        let registers = self.registers;
+        self.pc = next_pc;
+        let result = execute(self, instruction).map_err(|fault| fault.at(pc, op_code));
+        if let Some(budget) = &mut self.cycle_budget {
+            let skipped = self.pc == pc.wrapping_add(4);
+            *budget = budget.saturating_sub(vip_cycles(instruction, skipped, &registers));
+        }
+        result
+    }
+
+    /// Spends the rest of the frame's cycles, while the COSMAC VIP timing model is on
+    ///
+    /// `self` - The Cpu whose frame should end
+    fn end_frame(&mut self) {
+        if let Some(budget) = &mut self.cycle_budget {
+            *budget = 0;
+        }
+    }
+
+    /// Turns the COSMAC VIP timing model on or off. While it is on each instruction spends the
+    /// 1802 machine cycles vip_cycles estimates it took on the VIP out of a budget for each
+    /// frame, and Dxyn waits for the next frame to start before drawing. run_frame then runs as
+    /// many instructions as fit in the frame
+    ///
+    /// `self` - The Cpu whose timing model should be set
+    /// `on` - Whether the timing model should be on
+    pub fn set_vip_timing(&mut self, on: bool) {
+        self.cycle_budget = on.then_some(VIP_FRAME_BUDGET);
+        self.draw_held = false;
+    }
+
+    /// Returns true if the COSMAC VIP timing model is on
+    ///
+    /// `self` - The Cpu to check
+    pub fn has_vip_timing(&self) -> bool {
+        self.cycle_budget.is_some()
+    }
+
+    /// Counts the timers down once, as the frame starts, then runs one frame. With the COSMAC VIP
+    /// timing model on the frame lasts until its machine cycles are spent, otherwise it is the
+    /// number of instructions passed. The sound timer is left as the frame set it, so the host
+    /// can play the buzzer for the frame afterwards
+    ///
+    /// `self` - The Cpu which should run a frame
+    /// `instructions_per_frame` - The number of instructions to run, unless the timing model is on
+    pub fn run_frame(&mut self, instructions_per_frame: u32) -> Result<(), CpuError> {
+        self.tick_timers();
+        if self.has_vip_timing() {
+            while self.cycle_budget != Some(0) {
+                self.cycle()?;
+            }
+        } else {
+            for _ in 0..instructions_per_frame {
+                self.cycle()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the interpretations the Cpu uses for the ambiguous instructions
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Sets the interpretations the Cpu uses for the ambiguous instructions
+    ///
+    /// `self` - The Cpu whose quirks should be set
+    /// `quirks` - The new quirks
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        self.fit_screen(true);
+    }
+
+    /// Switches the screen to high or low resolution, clearing it unless the
+    /// resolution_change_keeps_screen quirk is on
+    ///
+    /// `self` - The Cpu whose screen should be switched
+    /// `hires` - True for the high resolution screen, false for the low resolution one
+    pub(crate) fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.screen_dirty = true;
+        self.fit_screen(self.quirks.resolution_change_keeps_screen);
+    }
+
+    /// Returns the number of screen pixels across each pixel of a sprite, 2 while the
+    /// half_pixel_lores_scroll quirk draws low resolution on the high resolution screen
+    ///
+    /// `self` - The Cpu to inspect
+    pub(crate) fn sprite_scale(&self) -> usize {
+        if !self.hires && self.quirks.half_pixel_lores_scroll {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Sizes the screen for the resolution it is in and the quirks
+    ///
+    /// `self` - The Cpu whose screen should be sized
+    /// `keep` - Whether to scale what is on the screen to the new size, instead of clearing it
+    fn fit_screen(&mut self, keep: bool) {
+        let (width, height) = if self.hires {
+            (HIRES_GRAPHICS_COLUMNS, HIRES_GRAPHICS_ROWS)
+        } else {
+            let (width, height) = self.low_resolution;
+            (width * self.sprite_scale(), height * self.sprite_scale())
+        };
+        if keep {
+            self.graphics.rescale(width, height);
+        } else {
+            self.graphics.resize(width, height);
+        }
+    }
+
+    /// Returns the instructions the Cpu decodes
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn instruction_set(&self) -> InstructionSet {
+        self.instruction_set
+    }
+
+    /// Sets the instructions the Cpu decodes, growing memory for XO-CHIP and MegaChip if it is
+    /// smaller than they can address, and adding the CHIP-8X colour map or the MegaChip screen.
+    /// Set it before loading a rom, so roms larger than 4K fit
+    ///
+    /// `self` - The Cpu whose instruction set should be set
+    /// `set` - The new instruction set
+    pub fn set_instruction_set(&mut self, set: InstructionSet) {
+        self.instruction_set = set;
+        let size = match set {
+            InstructionSet::XoChip => XO_MEM_SIZE,
+            InstructionSet::MegaChip => MEGA_MEM_SIZE,
+            _ => MEM_SIZE,
+        };
+        if self.memory.len() < size {
+            self.memory.resize(size, 0);
+        }
+        self.colour_map = set
+            .has_chip8x()
+            .then(|| ColourMap::new(self.graphics.width(), self.graphics.height()));
+        self.megachip = set.has_mega_chip().then(MegaChip::new);
+        self.set_megachip_colours();
+        self.screen_dirty = true;
+    }
+
+    /// Returns the bit planes which are drawn, cleared and scrolled
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn planes(&self) -> u8 {
+        self.planes
+    }
+
+    /// Returns true once the program has exited with 00FD. An exited Cpu executes nothing more
+    ///
+    /// `self` - The Cpu to check
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Returns the SUPER-CHIP RPL user flags, so the host can keep them between runs
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn rpl_flags(&self) -> &[u8; NUM_RPL_FLAGS] {
+        &self.rpl_flags
+    }
+
+    /// Sets the SUPER-CHIP RPL user flags, as kept by the host from an earlier run
+    ///
+    /// `self` - The Cpu whose flags should be set
+    /// `flags` - The saved flags
+    pub fn set_rpl_flags(&mut self, flags: [u8; NUM_RPL_FLAGS]) {
+        self.rpl_flags = flags;
+    }
+
+    /// Sets what the Cpu does when an instruction faults
+    ///
+    /// `self` - The Cpu whose fault policy should be set
+    /// `policy` - The new fault policy
+    pub fn set_fault_policy(&mut self, policy: FaultPolicy) {
+        self.fault_policy = policy;
+    }
+
+    /// Returns the fault the Cpu is halted on, if any
+    ///
+    /// `self` - The Cpu to check
+    pub fn fault(&self) -> Option<CpuError> {
+        self.fault
+    }
+
+    /// Clears the fault the Cpu is halted on, so the next cycle carries on after the faulting
+    /// instruction
+    ///
+    /// `self` - The Cpu which should resume
+    pub fn resume(&mut self) {
+        self.fault = None;
+    }
+
+    /// Counts the delay and sound timers down once. Hosts which run one frame every 60th of a
+    /// second should call this once per frame
+    ///
+    /// `self` - The Cpu whose timers should be counted down
+    pub fn tick_timers(&mut self) {
+        self.timers.tick();
+        self.start_frame();
+    }
+
+    /// Counts the delay and sound timers down at 60 Hz for the host time which has passed,
+    /// returning the number of ticks which happened
+    ///
+    /// `self` - The Cpu whose timers should be counted down
+    /// `elapsed` - The host time which has passed since the last call
+    pub fn update_timers(&mut self, elapsed: Duration) -> u32 {
+        let ticks = self.timers.advance(elapsed);
+        if ticks > 0 {
+            self.start_frame();
+        }
+        ticks
+    }
+
+    /// Ends the wait for the vertical blank and refills the frame's machine cycles, as the timers
+    /// tick
+    ///
+    /// `self` - The Cpu whose frame should start
+    fn start_frame(&mut self) {
+        self.vblank_wait = false;
+        if let Some(budget) = &mut self.cycle_budget {
+            *budget = VIP_FRAME_BUDGET;
+        }
+    }
+
+    /// Returns the delay and sound timers, so the audio and frontend can see their state
+    ///
+    /// `self` - The Cpu whose timers should be returned
+    pub fn timers(&self) -> &Timers {
+        &self.timers
+    }
+
+    /// Presses the key on the keypad, as the host sees a key go down
+    ///
+    /// `self` - The Cpu whose keypad should be updated
+    /// `key` - The key 0 .. F which was pressed
+    pub fn press_key(&mut self, key: u8) {
+        self.keypad.press(key);
+    }
 
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
+    /// Releases the key on the keypad, as the host sees a key go up
+    ///
+    /// `self` - The Cpu whose keypad should be updated
+    /// `key` - The key 0 .. F which was released
+    pub fn release_key(&mut self, key: u8) {
+        self.keypad.release(key);
+    }
+
+    /// Presses the key on the second keypad, which only CHIP-8X roms read
+    ///
+    /// `self` - The Cpu whose second keypad should be updated
+    /// `key` - The key 0 .. F which was pressed
+    pub fn press_second_key(&mut self, key: u8) {
+        self.second_keypad.press(key);
+    }
+
+    /// Releases the key on the second keypad, which only CHIP-8X roms read
+    ///
+    /// `self` - The Cpu whose second keypad should be updated
+    /// `key` - The key 0 .. F which was released
+    pub fn release_second_key(&mut self, key: u8) {
+        self.second_keypad.release(key);
+    }
+
+    /// Returns the last byte written to the output port by the CHIP-8E and CHIP-8I out
+    /// instructions
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn output_port(&self) -> u8 {
+        self.output_port
+    }
+
+    /// Puts a byte on the input port, for the next CHIP-8E or CHIP-8I in instruction to read
+    ///
+    /// `self` - The Cpu whose input port should be set
+    /// `value` - The byte to read
+    pub fn set_input_port(&mut self, value: u8) {
+        self.input_port = Some(value);
+    }
+
+    /// Returns true if the Cpu is halted on Fx0A, waiting for a key to be pressed and released
+    ///
+    /// `self` - The Cpu to check
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.key_wait.is_some()
+    }
+
+    /// Finishes an Fx0A wait if a key has been pressed and released since it started, storing
+    /// the key in the waiting register
+    ///
+    /// `self` - The Cpu which is waiting for a key
+    fn poll_key_wait(&mut self) {
+        if let (Some(x), Some(key)) = (self.key_wait, self.keypad.take_released()) {
+            self.registers[x] = key;
+            self.key_wait = None;
+        }
+    }
+
+    /// Returns the registers V0 .. VF
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn registers(&self) -> &[u8; NUM_REGISTERS] {
+        &self.registers
+    }
+
+    /// Returns the whole of memory
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Returns the value of the index register
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn index_register(&self) -> u32 {
+        self.index_register
+    }
+
+    /// Returns the address of the next instruction
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// Returns the return addresses on the call stack, oldest first
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    /// Returns the pixels on the screen, at its current resolution
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn graphics(&self) -> &Graphics {
+        &self.graphics
+    }
+
+    /// Returns the (width, height) of the screen being shown, the MegaChip screen while MegaChip
+    /// mode is on and the CHIP-8 screen otherwise
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn resolution(&self) -> (usize, usize) {
+        match &self.megachip {
+            Some(mega) if mega.is_enabled() => (MEGA_GRAPHICS_COLUMNS, MEGA_GRAPHICS_ROWS),
+            _ => (self.graphics.width(), self.graphics.height()),
         }
+    }
+
+    /// Returns the XO-CHIP audio pattern, or None if no rom has loaded one with F002
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn audio_pattern(&self) -> Option<&[u8; AUDIO_PATTERN_BYTES]> {
+        self.audio_pattern.as_ref()
+    }
+
+    /// Returns the pitch the XO-CHIP audio pattern is played at
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    /// Returns the MegaChip screen, or None if the instruction set is not MegaChip
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn megachip(&self) -> Option<&MegaChip> {
+        self.megachip.as_ref()
+    }
+
+    /// Returns the CHIP-8X colours of the screen, or None if the instruction set is not CHIP-8X
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn colour_map(&self) -> Option<&ColourMap> {
+        self.colour_map.as_ref()
+    }
+
+    /// Returns the keypad
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn keypad(&self) -> &Keypad {
+        &self.keypad
+    }
+
+    /// Returns true if the screen may have changed since it was last shown with present
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn is_screen_dirty(&self) -> bool {
+        self.screen_dirty
+    }
+
+    /// Returns the colours the screen is shown in
+    ///
+    /// `self` - The Cpu to inspect
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// Sets the colours the screen is shown in. CHIP-8X screens keep their own colours, and
+    /// MegaChip screens only take a 16 colour palette's colours for those the rom has not loaded
+    ///
+    /// `self` - The Cpu whose palette should be set
+    /// `palette` - The new colours
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.set_megachip_colours();
+        self.screen_dirty = true;
+    }
 
-        if self.sound_timer > 0 {
-            self.sound_timer -= 1;
+    /// Hands the colours of a 16 colour palette to the MegaChip screen, if there is one
+    ///
+    /// `self` - The Cpu whose MegaChip colours should be set
+    fn set_megachip_colours(&mut self) {
+        if let (Some(mega), Some(colours)) = (&mut self.megachip, self.palette.megachip_colours()) {
+            mega.set_base_colours(colours);
         }
     }
 
-    pub fn draw(&self, screen: &mut [u8]) {
-        for on in self.graphics.iter().zip() {
-            let color = if c.alive {
-                [0, 0xff, 0xff, 0xff]
-            } else {
-                [0, 0, c.heat, 0xff]
-            };
-            pix.copy_from_slice(&color);
+    /// Shows the screen on the sink, then marks it clean until an instruction changes it again
+    ///
+    /// `self` - The Cpu whose screen should be shown
+    /// `sink` - Where the screen should be shown
+    pub fn present<S: DisplaySink>(&mut self, sink: &mut S) -> Result<(), S::Error> {
+        sink.show(&Frame::new(self))?;
+        self.screen_dirty = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::RgbaImage;
+
+    fn cpu_with_program(program: &[u8]) -> Cpu {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.load_rom_bytes(program).unwrap();
+        cpu
+    }
+
+    #[test]
+    fn load_rom_bytes_copies_to_start_address() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.load_rom_bytes(&[0x12, 0x34]).unwrap();
+        assert_eq!(cpu.memory[0x200..0x202], [0x12, 0x34]);
+    }
+
+    #[test]
+    fn load_rom_reader_reads_whole_rom() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.load_rom_reader(&[0xab, 0xcd, 0xef][..]).unwrap();
+        assert_eq!(cpu.memory[0x200..0x203], [0xab, 0xcd, 0xef]);
+    }
+
+    #[test]
+    fn load_rom_rejects_empty_rom() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        assert!(matches!(cpu.load_rom_bytes(&[]), Err(RomError::Empty)));
+    }
+
+    #[test]
+    fn load_rom_rejects_rom_too_large() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        let rom = vec![0; MEM_SIZE - START_ADDRESS as usize + 1];
+        assert!(matches!(
+            cpu.load_rom_bytes(&rom),
+            Err(RomError::TooLarge { size, max }) if size == max + 1
+        ));
+    }
+
+    #[test]
+    fn load_rom_reports_missing_file() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        let result = cpu.load_rom(Path::new("no/such/rom.ch8"));
+        assert!(matches!(result, Err(RomError::Io(_))));
+    }
+
+    #[test]
+    fn halt_policy_stops_on_fault() {
+        let mut cpu = cpu_with_program(&[0x00, 0xee, 0x60, 0x01]);
+        let error = CpuError::StackUnderflow {
+            pc: 0x200,
+            op_code: 0x00ee,
+        };
+        assert_eq!(cpu.cycle(), Err(error));
+        assert_eq!(cpu.cycle(), Err(error));
+        assert_eq!(cpu.fault(), Some(error));
+
+        cpu.resume();
+        assert_eq!(cpu.cycle(), Ok(()));
+        assert_eq!(cpu.registers[0], 1);
+    }
+
+    #[test]
+    fn report_policy_returns_fault_and_continues() {
+        let mut cpu = cpu_with_program(&[0xff, 0xff, 0x60, 0x01]);
+        cpu.set_fault_policy(FaultPolicy::Report);
+        assert_eq!(
+            cpu.cycle(),
+            Err(CpuError::UnknownOpcode {
+                pc: 0x200,
+                op_code: 0xffff
+            })
+        );
+        assert_eq!(cpu.fault(), None);
+        assert_eq!(cpu.cycle(), Ok(()));
+        assert_eq!(cpu.registers[0], 1);
+    }
+
+    #[test]
+    fn ignore_policy_skips_fault() {
+        let mut cpu = cpu_with_program(&[0xff, 0xff, 0x60, 0x01]);
+        cpu.set_fault_policy(FaultPolicy::Ignore);
+        assert_eq!(cpu.cycle(), Ok(()));
+        assert_eq!(cpu.cycle(), Ok(()));
+        assert_eq!(cpu.registers[0], 1);
+    }
+
+    #[test]
+    fn with_platform_sets_up_the_platform() {
+        let cpu = Cpu::with_platform(Platform::SchipModern);
+        assert_eq!(cpu.instruction_set(), InstructionSet::SuperChip);
+        assert_eq!(cpu.quirks(), Quirks::schip());
+        assert_eq!(cpu.memory().len(), MEM_SIZE);
+        assert_eq!(cpu.pc(), START_ADDRESS);
+
+        let cpu = Cpu::with_platform(Platform::XoChip);
+        assert_eq!(cpu.memory().len(), XO_MEM_SIZE);
+        assert_eq!(cpu.quirks(), Quirks::xo_chip());
+    }
+
+    #[test]
+    fn hires_chip8_skips_the_interpreter_patch() {
+        let mut cpu = Cpu::with_platform(Platform::HiresChip8);
+        assert_eq!(cpu.graphics().height(), TWO_PAGE_GRAPHICS_ROWS);
+        cpu.load_rom_bytes(&[0x12, 0x60, 0x00, 0x00]).unwrap();
+        assert_eq!(cpu.pc(), TWO_PAGE_PROGRAM_START);
+
+        let mut cpu = Cpu::with_platform(Platform::HiresChip8);
+        cpu.load_rom_bytes(&[0x12, 0x04, 0x00, 0x00]).unwrap();
+        assert_eq!(cpu.pc(), START_ADDRESS);
+    }
+
+    #[test]
+    fn chip8x_draws_in_vp590_colours() {
+        let mut cpu = Cpu::with_platform(Platform::Chip8X);
+        assert_eq!(cpu.pc(), 0x300);
+        cpu.graphics.set(0, 0, DEFAULT_PLANE);
+        let mut image = RgbaImage::new();
+        cpu.present(&mut image).unwrap();
+        assert_eq!(image.pixel(0, 0), VP590_FOREGROUNDS[1]);
+        assert_eq!(image.pixel(1, 0), VP590_BACKGROUNDS[0]);
+    }
+
+    #[test]
+    fn config_sets_memory_and_start_address() {
+        let mut cpu = Cpu::new(CpuConfig {
+            memory_size: 2 * KILOBYTE,
+            start_address: 0x600,
+            ..CpuConfig::default()
+        });
+        assert_eq!(cpu.memory().len(), 2 * KILOBYTE);
+        assert_eq!(cpu.pc(), 0x600);
+        assert!(matches!(
+            cpu.load_rom_bytes(&[0; 0x201]),
+            Err(RomError::TooLarge { max: 0x200, .. })
+        ));
+
+        let cpu = Cpu::with_platform(Platform::Eti660);
+        assert_eq!(cpu.pc(), 0x600);
+        assert_eq!(cpu.graphics().height(), 48);
+    }
+
+    #[test]
+    fn fx29_points_at_each_platforms_font() {
+        for (platform, address) in [
+            (Platform::Vip, 0x110),
+            (Platform::HiresChip8, 0x110),
+            (Platform::Chip8E, 0x110),
+            (Platform::Chip8I, 0x110),
+            (Platform::Chip10, 0x110),
+            (Platform::Eti660, 0x50),
+            (Platform::Chip8X, 0x110),
+            (Platform::Chip48, 0x50),
+            (Platform::SchipLegacy, 0x50),
+            (Platform::SchipModern, 0),
+            (Platform::XoChip, 0),
+            (Platform::MegaChip, 0x50),
+        ] {
+            let mut cpu = Cpu::with_platform(platform);
+            let start = platform.start_address() as usize;
+            cpu.load_rom_bytes(&[0x60, 0x0a, 0xf0, 0x29]).unwrap();
+            cpu.cycle().unwrap();
+            cpu.cycle().unwrap();
+            let digit = address + 0xa * 5;
+            assert_eq!(cpu.index_register(), digit, "{}", platform.name());
+            let sprite = &platform.font().small[0xa * 5..0xb * 5];
+            assert_eq!(&cpu.memory()[digit as usize..][..5], sprite);
+            assert!(digit as usize + 5 <= start);
         }
     }
+
+    #[test]
+    fn vip_timing_runs_as_many_instructions_as_fit_in_a_frame() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_vip_timing(true);
+        cpu.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+        cpu.run_frame(1).unwrap();
+        // 7xnn costs 26 machine cycles and 1nnn 28, so 48 loops fit in the frame's 2598 with 6
+        // left, which are enough to start one more 7xnn
+        let registers = [0; NUM_REGISTERS];
+        let add = vip_cycles(Instruction::AddVxByte { x: 0, nn: 1 }, false, &registers);
+        let jp = vip_cycles(Instruction::JpAddr { nnn: 0x200 }, false, &registers);
+        assert_eq!((add, jp, VIP_FRAME_BUDGET), (26, 28, 2598));
+        assert_eq!(cpu.registers()[0], 49);
+        cpu.run_frame(1).unwrap();
+        assert_eq!(cpu.registers()[0], 97);
+    }
+
+    #[test]
+    fn vip_timing_draws_after_waiting_for_the_next_frame() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_vip_timing(true);
+        #[rustfmt::skip]
+        let rom = [
+            0xa0, 0x50, 0xd0, 0x01, 0x70, 0x01, 0x12, 0x02,
+        ];
+        cpu.load_rom_bytes(&rom).unwrap();
+        cpu.run_frame(1).unwrap();
+        assert_eq!(cpu.pc(), 0x202);
+        assert!(!cpu.graphics().get(0, 0));
+        cpu.run_frame(1).unwrap();
+        assert!(cpu.graphics().get(0, 0));
+        assert_eq!(cpu.registers()[0], 1);
+        assert_eq!(cpu.pc(), 0x202);
+    }
+
+    #[test]
+    fn chip10_starts_in_128x64() {
+        let cpu = Cpu::with_platform(Platform::Chip10);
+        assert_eq!(cpu.instruction_set(), InstructionSet::Chip8);
+        assert_eq!(
+            cpu.resolution(),
+            (HIRES_GRAPHICS_COLUMNS, HIRES_GRAPHICS_ROWS)
+        );
+    }
+
+    #[test]
+    fn xo_chip_grows_memory_to_64k() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        assert!(cpu.load_rom_bytes(&[0; 8 * KILOBYTE]).is_err());
+        cpu.set_instruction_set(InstructionSet::XoChip);
+        assert_eq!(cpu.memory().len(), XO_MEM_SIZE);
+        cpu.load_rom_bytes(&[0; 8 * KILOBYTE]).unwrap();
+    }
+
+    #[test]
+    fn fetch_past_end_of_memory_faults() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.pc = (MEM_SIZE - 1) as u16;
+        assert_eq!(
+            cpu.cycle(),
+            Err(CpuError::PcOutOfBounds {
+                pc: (MEM_SIZE - 1) as u16
+            })
+        );
+    }
+
+    #[test]
+    fn fetch_at_the_end_of_64k_faults_instead_of_overflowing() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_instruction_set(InstructionSet::XoChip);
+        cpu.pc = 0xfffe;
+        assert_eq!(cpu.cycle(), Err(CpuError::PcOutOfBounds { pc: 0xfffe }));
+        assert_eq!(cpu.pc, 0xfffe);
+    }
 }
@@ -1,90 +1,356 @@
-use crate::cpu::GRAPHICS_COLUMNS;
-use crate::cpu::GRAPHICS_ROWS;
+use crate::cpu::colour::{ZONE_BLOCK_ROWS, ZONE_WIDTH};
+use crate::cpu::error::Fault;
+use crate::cpu::instruction::Instruction;
+use crate::cpu::megachip::{BlendMode, DigitizedSound};
+use crate::cpu::Cpu;
+use crate::cpu::ALL_PLANES;
+use crate::cpu::AUDIO_PATTERN_BYTES;
+use crate::cpu::NUM_RPL_FLAGS;
 use rand::Rng;
 
-/// Executes the op code passed
-///	cpu - The Cpu to execute the operation on
-///	op_code - The opcode to execute
-pub fn exec_opcode(cpu: &mut Cpu, op_code: u16) {
-    match get_nibbles(op_code) {
-        [0, 0, 0xe, 0] => cls(cpu),
-        [0, 0, 0xe, 0xe] => ret(cpu),
-        [1, n1, n2, n3] => jp_addr(cpu, nibbles_to_u16(n1, n2, n3)),
-        [2, n1, n2, n3] => call_addr(cpu, nibbles_to_u16(n1, n2, n3)),
-        [3, x, n1, n2] => se_vx_byte(cpu, x as usize, nibbles_to_u8(n1, n2)),
-        [4, x, n1, n2] => sne_vx_byte(cpu, x as usize, nibbles_to_u8(n1, n2)),
-        [5, x, y, 0] => se_vx_vy(cpu, x as usize, y as usize),
-        [6, x, n1, n2] => ld_vx_byte(cpu, x as usize, nibbles_to_u8(n1, n2)),
-        [7, x, n1, n2] => add_vx_byte(cpu, x as usize, nibbles_to_u8(n1, n2)),
-        [8, x, y, 0] => ld_vx_vy(cpu, x as usize, y as usize),
-        [8, x, y, 1] => or_vx_vy(cpu, x as usize, y as usize),
-        [8, x, y, 2] => and_vx_vy(cpu, x as usize, y as usize),
-        [8, x, y, 3] => xor_vx_vy(cpu, x as usize, y as usize),
-        [8, x, y, 4] => add_vx_vy(cpu, x as usize, y as usize),
-        [8, x, y, 5] => sub_vx_vy(cpu, x as usize, y as usize),
-        [8, x, _, 6] => shr_vx_vy(cpu, x as usize),
-        [8, x, y, 7] => subn_vx_vy(cpu, x as usize, y as usize),
-        [8, x, _, _e] => shl_vx_vy(cpu, x as usize),
-        [9, x, y, 0] => sne_vx_vy(cpu, x as usize, y as usize),
-        [0xa, n1, n2, n3] => ld_i_addr(cpu, nibbles_to_u16(n1, n2, n3)),
-        [0xb, n1, n2, n3] => jp_v0_addr(cpu, nibbles_to_u16(n1, n2, n3)),
-        [0xc, x, n1, n2] => rnd_vx_byte(cpu, x as usize, nibbles_to_u8(n1, n2)),
-        [0xd, x, y, n] => drw_vx_vy_nibble(cpu, x as usize, y as usize, n as usize),
-        _ => (),
-    }
-}
-
-// Represents each half byte (a nibble) in a 2-byte number
-type Nibbles = [u8; 4];
-
-// Masks out the first nibble from a 2-byte numbers
-const NIBBLE_ONE_MASK: u16 = 0xf000;
-// Masks out the second nibble from a 2-byte numbers
-const NIBBLE_TWO_MASK: u16 = 0x0f00;
-// Masks out the third nibble from a 2-byte numbers
-const NIBBLE_THREE_MASK: u16 = 0x00f0;
-// Masks out the fourth nibble from a 2-byte numbers
-const NIBBLE_FOUR_MASK: u16 = 0xf00f;
-
-/// Returns the Nibbles (half a byte each) from a 2 byte number
-///	op_code - The two byte value representing an instruction
-fn get_nibbles(op_code: u16) -> Nibbles {
-    [
-        ((op_code & NIBBLE_ONE_MASK) >> 12).try_into().unwrap(),
-        ((op_code & NIBBLE_TWO_MASK) >> 8).try_into().unwrap(),
-        ((op_code & NIBBLE_THREE_MASK) >> 4).try_into().unwrap(),
-        (op_code & NIBBLE_FOUR_MASK).try_into().unwrap(),
-    ]
-}
-
-fn nibbles_to_u8(n1: u8, n2: u8) -> u8 {
-    assert!(n1.leading_zeros() >= 4);
-    assert!(n2.leading_zeros() >= 4);
-    n1 << 4 | n2
-}
-
-fn nibbles_to_u16(n1: u8, n2: u8, n3: u8) -> u16 {
-    assert!(n1.leading_zeros() >= 4);
-    assert!(n2.leading_zeros() >= 4);
-    assert!(n3.leading_zeros() >= 4);
-    (n1 as u16) << 8 | (n2 as u16) << 4 | (n3 as u16)
+/// Executes the decoded instruction passed, returning the fault if it could not be executed
+///	cpu - The Cpu to execute the instruction on
+///	instruction - The instruction to execute
+pub fn execute(cpu: &mut Cpu, instruction: Instruction) -> Result<(), Fault> {
+    use Instruction::*;
+
+    match instruction {
+        MegaOff => mega_off(cpu),
+        MegaOn => mega_on(cpu),
+        LdILongByte { nn } => ld_i_long_byte(cpu, nn)?,
+        LdPalByte { nn } => ld_pal_byte(cpu, nn as usize)?,
+        SprWidthByte { nn } => spr_width_byte(cpu, nn),
+        SprHeightByte { nn } => spr_height_byte(cpu, nn),
+        AlphaByte { nn } => alpha_byte(cpu, nn),
+        DigiSndNibble { n } => digi_snd_nibble(cpu, n)?,
+        StopSnd => stop_snd(cpu),
+        BlendNibble { n } => blend_nibble(cpu, n)?,
+        CcolByte { nn } => ccol_byte(cpu, nn),
+        WaitDt => wait_dt(cpu),
+        Skip => skip_next_instruction(cpu)?,
+        NextBackground => next_background(cpu),
+        Cls => cls(cpu),
+        Ret => ret(cpu)?,
+        ScdNibble { n } => scd_nibble(cpu, n as usize),
+        ScuNibble { n } => scu_nibble(cpu, n as usize),
+        Scr => scr(cpu),
+        Scl => scl(cpu),
+        Exit => exit(cpu),
+        Low => low(cpu),
+        High => high(cpu),
+        JpAddr { nnn } => jp_addr(cpu, nnn),
+        CallAddr { nnn } => call_addr(cpu, nnn)?,
+        SeVxByte { x, nn } => se_vx_byte(cpu, x as usize, nn)?,
+        SneVxByte { x, nn } => sne_vx_byte(cpu, x as usize, nn)?,
+        SeVxVy { x, y } => se_vx_vy(cpu, x as usize, y as usize)?,
+        SgtVxVy { x, y } => sgt_vx_vy(cpu, x as usize, y as usize)?,
+        AddNibblesVxVy { x, y } => add_nibbles_vx_vy(cpu, x as usize, y as usize),
+        LdIVxVy { x, y } => ld_i_vx_vy(cpu, x as usize, y as usize)?,
+        LdVxVyI { x, y } => ld_vx_vy_i(cpu, x as usize, y as usize)?,
+        LdVxByte { x, nn } => ld_vx_byte(cpu, x as usize, nn),
+        AddVxByte { x, nn } => add_vx_byte(cpu, x as usize, nn),
+        LdVxVy { x, y } => ld_vx_vy(cpu, x as usize, y as usize),
+        OrVxVy { x, y } => or_vx_vy(cpu, x as usize, y as usize),
+        AndVxVy { x, y } => and_vx_vy(cpu, x as usize, y as usize),
+        XorVxVy { x, y } => xor_vx_vy(cpu, x as usize, y as usize),
+        AddVxVy { x, y } => add_vx_vy(cpu, x as usize, y as usize),
+        SubVxVy { x, y } => sub_vx_vy(cpu, x as usize, y as usize),
+        ShrVxVy { x, y } => shr_vx_vy(cpu, x as usize, y as usize),
+        SubnVxVy { x, y } => subn_vx_vy(cpu, x as usize, y as usize),
+        ShlVxVy { x, y } => shl_vx_vy(cpu, x as usize, y as usize),
+        SneVxVy { x, y } => sne_vx_vy(cpu, x as usize, y as usize)?,
+        LdIAddr { nnn } => ld_i_addr(cpu, nnn),
+        JpV0Addr { nnn } => jp_v0_addr(cpu, nnn),
+        JpBackByte { nn } => jp_back_byte(cpu, nn),
+        JpFwdByte { nn } => jp_fwd_byte(cpu, nn),
+        ColVxVyNibble { x, y, n } => col_vx_vy_nibble(cpu, x as usize, y as usize, n as usize),
+        RndVxByte { x, nn } => rnd_vx_byte(cpu, x as usize, nn),
+        DrwVxVyNibble { x, y, n } => drw_vx_vy_nibble(cpu, x as usize, y as usize, n as usize)?,
+        SkpVx { x } => skp_vx(cpu, x as usize)?,
+        SknpVx { x } => sknp_vx(cpu, x as usize)?,
+        Skp2Vx { x } => skp2_vx(cpu, x as usize)?,
+        Sknp2Vx { x } => sknp2_vx(cpu, x as usize)?,
+        LdILong => ld_i_long(cpu)?,
+        OutVx { x } => out_vx(cpu, x as usize),
+        InVx { x } => in_vx(cpu, x as usize),
+        InWaitVx { x } => in_wait_vx(cpu, x as usize),
+        SkipVx { x } => skip_vx(cpu, x as usize),
+        LdDtVxWait { x } => ld_dt_vx_wait(cpu, x as usize),
+        Plane { n } => plane(cpu, n),
+        Audio => audio(cpu)?,
+        PitchVx { x } => pitch_vx(cpu, x as usize),
+        LdVxDt { x } => ld_vx_dt(cpu, x as usize),
+        LdVxK { x } => ld_vx_k(cpu, x as usize),
+        LdDtVx { x } => ld_dt_vx(cpu, x as usize),
+        LdStVx { x } => ld_st_vx(cpu, x as usize),
+        AddIVx { x } => add_i_vx(cpu, x as usize),
+        LdFVx { x } => ld_f_vx(cpu, x as usize),
+        LdHfVx { x } => ld_hf_vx(cpu, x as usize),
+        LdBVx { x } => ld_b_vx(cpu, x as usize)?,
+        LdIVx { x } => ld_i_vx(cpu, x as usize)?,
+        LdVxI { x } => ld_vx_i(cpu, x as usize)?,
+        LdRVx { x } => ld_r_vx(cpu, x as usize),
+        LdVxR { x } => ld_vx_r(cpu, x as usize),
+        Sys { .. } => (),
+        Unknown(_) => return Err(Fault::UnknownOpcode),
+    }
+    Ok(())
 }
 
 // Represents the register which is used to store flags about operations
 const FLAG_REGISTER: usize = 0xf;
 
-/// Clears the screen
+// The number of bytes in each font sprite
+const FONT_SPRITE_BYTES: usize = 5;
+
+// The number of bytes in each big font sprite
+const BIG_FONT_SPRITE_BYTES: usize = 10;
+
+// The width and height of the sprites drawn by Dxy0 on SUPER-CHIP
+const BIG_SPRITE_SIZE: usize = 16;
+
+// The number of columns 00FB and 00FC scroll by
+const HORIZONTAL_SCROLL: usize = 4;
+
+// The first byte pair of F000 nnnn, the only XO-CHIP instruction which is four bytes long
+const LONG_INSTRUCTION: [u8; 2] = [0xf0, 0x00];
+
+// The first byte of 01nn nnnn, the only MegaChip instruction which is four bytes long
+const MEGA_LONG_INSTRUCTION: u8 = 0x01;
+
+// The number of bytes before the samples of a MegaChip digitized sound: the sample rate in
+// two bytes, the number of samples in three and a zero
+const DIGITIZED_SOUND_HEADER: usize = 6;
+
+/// Returns the range of len bytes of memory starting at addr, or a fault if any of it is past
+/// the end of memory
+///	cpu - The Cpu whose memory the range is in
+///	addr - The first address in the range
+///	len - The number of bytes in the range
+fn memory_range(cpu: &Cpu, addr: usize, len: usize) -> Result<std::ops::Range<usize>, Fault> {
+    let size = cpu.memory.len();
+    if addr + len > size {
+        return Err(Fault::MemoryOutOfBounds {
+            addr: addr.max(size),
+        });
+    }
+    Ok(addr..addr + len)
+}
+
+/// Returns the program counter moved forward by the bytes, or a fault if that runs off the end
+/// of the 64K address space
+///	cpu - The Cpu whose program counter should be moved
+///	bytes - The number of bytes to move it by
+fn advance_pc(cpu: &Cpu, bytes: u16) -> Result<u16, Fault> {
+    cpu.pc.checked_add(bytes).ok_or(Fault::MemoryOutOfBounds {
+        addr: cpu.memory.len(),
+    })
+}
+
+/// Moves the program counter past the next instruction, which is four bytes long if it is the
+/// XO-CHIP F000 nnnn or the MegaChip 01nn nnnn
+///	cpu - The Cpu whose next instruction should be skipped
+fn skip_next_instruction(cpu: &mut Cpu) -> Result<(), Fault> {
+    let pc = cpu.pc as usize;
+    let long = (cpu.instruction_set.has_xo_chip()
+        && cpu.memory.get(pc..pc + 2) == Some(&LONG_INSTRUCTION[..]))
+        || (cpu.instruction_set.has_mega_chip()
+            && cpu.memory.get(pc) == Some(&MEGA_LONG_INSTRUCTION));
+    cpu.pc = advance_pc(cpu, if long { 4 } else { 2 })?;
+    Ok(())
+}
+
+/// Turns MegaChip mode off, going back to the CHIP-8 screen
+///	cpu - The Cpu which we should execute this instruction on
+fn mega_off(cpu: &mut Cpu) {
+    cpu.screen_dirty = true;
+    if let Some(mega) = &mut cpu.megachip {
+        mega.set_enabled(false);
+    }
+}
+
+/// Turns MegaChip mode on, showing the 256x192 palette screen
+///	cpu - The Cpu which we should execute this instruction on
+fn mega_on(cpu: &mut Cpu) {
+    cpu.screen_dirty = true;
+    if let Some(mega) = &mut cpu.megachip {
+        mega.set_enabled(true);
+    }
+}
+
+/// Sets the index register to the 24 bit address made of the byte and the two bytes following
+/// the instruction, then moves the program counter past them
+///	cpu - The Cpu which we should execute this instruction on
+///	byte - The highest byte of the address
+fn ld_i_long_byte(cpu: &mut Cpu, byte: u8) -> Result<(), Fault> {
+    let addr = memory_range(cpu, cpu.pc as usize, 2)?;
+    let low = (cpu.memory[addr.start] as u32) << 8 | cpu.memory[addr.start + 1] as u32;
+    cpu.index_register = (byte as u32) << 16 | low;
+    cpu.pc = advance_pc(cpu, 2)?;
+    Ok(())
+}
+
+/// Loads palette colours 1 through count from memory, starting at the index register, four
+/// ARGB bytes each
+///	cpu - The Cpu which we should execute this instruction on
+///	count - The number of colours to load
+fn ld_pal_byte(cpu: &mut Cpu, count: usize) -> Result<(), Fault> {
+    let range = memory_range(cpu, cpu.index_register as usize, count * 4)?;
+    if let Some(mega) = &mut cpu.megachip {
+        mega.load_palette(&cpu.memory[range]);
+    }
+    Ok(())
+}
+
+/// Sets the width of the MegaChip sprites drawn by Dxyn
+///	cpu - The Cpu which we should execute this instruction on
+///	byte - The number of pixels across each sprite, 0 meaning 256
+fn spr_width_byte(cpu: &mut Cpu, byte: u8) {
+    if let Some(mega) = &mut cpu.megachip {
+        mega.set_sprite_size(Some(byte), None);
+    }
+}
+
+/// Sets the height of the MegaChip sprites drawn by Dxyn
+///	cpu - The Cpu which we should execute this instruction on
+///	byte - The number of rows in each sprite, 0 meaning 256
+fn spr_height_byte(cpu: &mut Cpu, byte: u8) {
+    if let Some(mega) = &mut cpu.megachip {
+        mega.set_sprite_size(None, Some(byte));
+    }
+}
+
+/// Sets the opacity of the whole MegaChip screen
+///	cpu - The Cpu which we should execute this instruction on
+///	byte - 0 for black, 255 for fully shown
+fn alpha_byte(cpu: &mut Cpu, byte: u8) {
+    if let Some(mega) = &mut cpu.megachip {
+        mega.set_alpha(byte);
+    }
+}
+
+/// Plays the digitized sound at the index register, a 6 byte header followed by its samples,
+/// faulting if any of it is past the end of memory
+///	cpu - The Cpu which we should execute this instruction on
+///	nibble - 0 to loop the sound, anything else to play it once
+fn digi_snd_nibble(cpu: &mut Cpu, nibble: u8) -> Result<(), Fault> {
+    let header = memory_range(cpu, cpu.index_register as usize, DIGITIZED_SOUND_HEADER)?;
+    let bytes = &cpu.memory[header.clone()];
+    let sample_rate = (bytes[0] as u16) << 8 | bytes[1] as u16;
+    let len = (bytes[2] as usize) << 16 | (bytes[3] as usize) << 8 | bytes[4] as usize;
+    let samples = memory_range(cpu, header.end, len)?;
+    if let Some(mega) = &mut cpu.megachip {
+        mega.set_sound(Some(DigitizedSound {
+            start: samples.start,
+            len,
+            sample_rate,
+            looping: nibble == 0,
+        }));
+    }
+    Ok(())
+}
+
+/// Stops the digitized sound
+///	cpu - The Cpu which we should execute this instruction on
+fn stop_snd(cpu: &mut Cpu) {
+    if let Some(mega) = &mut cpu.megachip {
+        mega.set_sound(None);
+    }
+}
+
+/// Sets how MegaChip sprites are mixed onto the screen, faulting if nibble is not a blend mode
+///	cpu - The Cpu which we should execute this instruction on
+///	nibble - The blend mode, 0 .. 5
+fn blend_nibble(cpu: &mut Cpu, nibble: u8) -> Result<(), Fault> {
+    let blend_mode = BlendMode::from_nibble(nibble).ok_or(Fault::UnknownOpcode)?;
+    if let Some(mega) = &mut cpu.megachip {
+        mega.set_blend_mode(blend_mode);
+    }
+    Ok(())
+}
+
+/// Sets the palette colour MegaChip sprites collide with
+///	cpu - The Cpu which we should execute this instruction on
+///	byte - The palette colour
+fn ccol_byte(cpu: &mut Cpu, byte: u8) {
+    if let Some(mega) = &mut cpu.megachip {
+        mega.set_collision_colour(byte);
+    }
+}
+
+/// Moves the CHIP-8X colour map on to the next background colour
+///	cpu - The Cpu which we should execute this instruction on
+fn next_background(cpu: &mut Cpu) {
+    cpu.screen_dirty = true;
+    if let Some(colour_map) = &mut cpu.colour_map {
+        colour_map.next_background();
+    }
+}
+
+/// Clears the selected planes of the screen. In MegaChip mode this shows the sprites drawn
+/// since the last 00E0 and starts a new frame
 ///	cpu - The Cpu which we should execute this instruction on
 fn cls(cpu: &mut Cpu) {
-    cpu.graphics
-        .iter_mut()
-        .for_each(|x| x.iter_mut().for_each(|x| x = false));
+    cpu.screen_dirty = true;
+    cpu.graphics.clear(cpu.planes);
+    if let Some(mega) = cpu.megachip.as_mut().filter(|mega| mega.is_enabled()) {
+        mega.present();
+    }
 }
 
-/// Returns from a subroutine
+/// Scrolls the selected planes of the screen down by nibble rows
 ///	cpu - The Cpu which we should execute this instruction on
-fn ret(cpu: &mut Cpu) {
-    cpu.sp -= 1;
+///	nibble - The number of rows to scroll by
+fn scd_nibble(cpu: &mut Cpu, nibble: usize) {
+    cpu.screen_dirty = true;
+    cpu.graphics.scroll_down(nibble, cpu.planes);
+}
+
+/// Scrolls the selected planes of the screen up by nibble rows
+///	cpu - The Cpu which we should execute this instruction on
+///	nibble - The number of rows to scroll by
+fn scu_nibble(cpu: &mut Cpu, nibble: usize) {
+    cpu.screen_dirty = true;
+    cpu.graphics.scroll_up(nibble, cpu.planes);
+}
+
+/// Scrolls the selected planes of the screen right by 4 columns
+///	cpu - The Cpu which we should execute this instruction on
+fn scr(cpu: &mut Cpu) {
+    cpu.screen_dirty = true;
+    cpu.graphics.scroll_right(HORIZONTAL_SCROLL, cpu.planes);
+}
+
+/// Scrolls the selected planes of the screen left by 4 columns
+///	cpu - The Cpu which we should execute this instruction on
+fn scl(cpu: &mut Cpu) {
+    cpu.screen_dirty = true;
+    cpu.graphics.scroll_left(HORIZONTAL_SCROLL, cpu.planes);
+}
+
+/// Exits the interpreter, the Cpu executes nothing after this
+///	cpu - The Cpu which we should execute this instruction on
+fn exit(cpu: &mut Cpu) {
+    cpu.exited = true;
+}
+
+/// Switches to the low resolution screen, clearing it unless the quirks keep it
+///	cpu - The Cpu which we should execute this instruction on
+fn low(cpu: &mut Cpu) {
+    cpu.set_hires(false);
+}
+
+/// Switches to the high resolution screen, clearing it unless the quirks keep it
+///	cpu - The Cpu which we should execute this instruction on
+fn high(cpu: &mut Cpu) {
+    cpu.set_hires(true);
+}
+
+/// Returns from a subroutine, faulting if there is nothing on the call stack
+///	cpu - The Cpu which we should execute this instruction on
+fn ret(cpu: &mut Cpu) -> Result<(), Fault> {
+    cpu.pc = cpu.stack.pop().ok_or(Fault::StackUnderflow)?;
+    Ok(())
 }
 
 /// Jumps to the address, addr
@@ -94,42 +360,126 @@ fn jp_addr(cpu: &mut Cpu, addr: u16) {
     cpu.pc = addr;
 }
 
-/// Calls the subroutine at address, addr
+/// Calls the subroutine at address, addr, faulting if the call stack is as deep as it can go
 ///	cpu - The Cpu which we should execute this instruction on
 ///	addr - The address where we should call the subroutine
-fn call_addr(cpu: &mut Cpu, addr: u16) {
-    cpu.stack[cpu.sp as usize] = cpu.pc;
-    cpu.sp += 1;
+fn call_addr(cpu: &mut Cpu, addr: u16) -> Result<(), Fault> {
+    if cpu
+        .stack_depth
+        .is_some_and(|depth| cpu.stack.len() >= depth)
+    {
+        return Err(Fault::StackOverflow);
+    }
+    cpu.stack.push(cpu.pc);
     cpu.pc = addr;
+    Ok(())
 }
 
 /// Skips the next instruction, if the data in register x is equal to kk
 ///	cpu - The Cpu which we should execute this instruction on
 ///	x - The register we check equality for
 ///	kk - The value we check equality for
-fn se_vx_byte(cpu: &mut Cpu, x: usize, kk: u8) {
+fn se_vx_byte(cpu: &mut Cpu, x: usize, kk: u8) -> Result<(), Fault> {
     if cpu.registers[x] == kk {
-        cpu.pc += 2;
+        skip_next_instruction(cpu)?;
     }
+    Ok(())
 }
 
 /// Skips the next instruction, if the data in register x is not equal to kk
 ///	cpu - The Cpu which we should execute this instruction on
 ///	x - The register to check for inequality
 ///	kk - The value to check for inequality
-fn sne_vx_byte(cpu: &mut Cpu, x: usize, kk: u8) {
+fn sne_vx_byte(cpu: &mut Cpu, x: usize, kk: u8) -> Result<(), Fault> {
     if cpu.registers[x] != kk {
-        cpu.pc += 2;
+        skip_next_instruction(cpu)?;
     }
+    Ok(())
 }
 
 /// Skips the next instruction, if the data in register x is equal to the data in register y
 ///	cpu - The Cpu which we should execute this instruction on
 ///	x - The register to check for inequality
 ///	y - The other register to check for inequality
-fn se_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
+fn se_vx_vy(cpu: &mut Cpu, x: usize, y: usize) -> Result<(), Fault> {
     if cpu.registers[x] == cpu.registers[y] {
-        cpu.pc += 2;
+        skip_next_instruction(cpu)?;
+    }
+    Ok(())
+}
+
+/// Skips the next instruction, if the data in register x is greater than the data in register y
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register to compare
+///	y - The other register to compare
+fn sgt_vx_vy(cpu: &mut Cpu, x: usize, y: usize) -> Result<(), Fault> {
+    if cpu.registers[x] > cpu.registers[y] {
+        skip_next_instruction(cpu)?;
+    }
+    Ok(())
+}
+
+/// Adds each nibble of register y to the same nibble of register x, each wrapping at 8, then
+/// stores the result in register x
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register which will be set, added to the other register
+///	y - The other register which will be added
+fn add_nibbles_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
+    let (vx, vy) = (cpu.registers[x], cpu.registers[y]);
+    let high = ((vx >> 4) + (vy >> 4)) % 8;
+    let low = ((vx & 0xf) + (vy & 0xf)) % 8;
+    cpu.registers[x] = high << 4 | low;
+}
+
+/// Stores registers x through y in memory, starting at the index register. If x is after y the
+/// registers are stored in reverse order. The index register is left alone, except on CHIP-8E
+/// where it moves past the stored registers
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The first register which will be stored
+///	y - The last register which will be stored
+fn ld_i_vx_vy(cpu: &mut Cpu, x: usize, y: usize) -> Result<(), Fault> {
+    let range = memory_range(cpu, cpu.index_register as usize, x.abs_diff(y) + 1)?;
+    for (addr, register) in range.zip(register_range(x, y)) {
+        cpu.memory[addr] = cpu.registers[register];
+    }
+    increment_i_past_range(cpu, x, y);
+    Ok(())
+}
+
+/// Reads registers x through y from memory, starting at the index register. If x is after y
+/// the registers are read in reverse order. The index register is left alone, except on
+/// CHIP-8E where it moves past the read registers
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The first register which will be read
+///	y - The last register which will be read
+fn ld_vx_vy_i(cpu: &mut Cpu, x: usize, y: usize) -> Result<(), Fault> {
+    let range = memory_range(cpu, cpu.index_register as usize, x.abs_diff(y) + 1)?;
+    for (addr, register) in range.zip(register_range(x, y)) {
+        cpu.registers[register] = cpu.memory[addr];
+    }
+    increment_i_past_range(cpu, x, y);
+    Ok(())
+}
+
+/// Moves the index register past registers x through y on CHIP-8E, after they were stored or
+/// read by 5xy2 or 5xy3
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The first register which was stored or read
+///	y - The last register which was stored or read
+fn increment_i_past_range(cpu: &mut Cpu, x: usize, y: usize) {
+    if cpu.instruction_set.has_chip8e() {
+        cpu.index_register += x.abs_diff(y) as u32 + 1;
+    }
+}
+
+/// Returns the registers from x to y, counting down if x is after y
+///	x - The first register
+///	y - The last register
+fn register_range(x: usize, y: usize) -> Box<dyn Iterator<Item = usize>> {
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
     }
 }
 
@@ -144,15 +494,15 @@ fn ld_vx_byte(cpu: &mut Cpu, x: usize, kk: u8) {
 /// Adds kk to the value that is currently in register x, then sets register x to the result
 ///	cpu - The Cpu which we should execute this instruction on
 fn add_vx_byte(cpu: &mut Cpu, x: usize, kk: u8) {
-    cpu.registers[x] += kk;
+    cpu.registers[x] = cpu.registers[x].wrapping_add(kk);
 }
 
-/// Sets the value of register y to the value of register x
+/// Sets the value of register x to the value of register y
 ///	cpu - The Cpu which we should execute this instruction on
-///	x - The register whose data will be set to other register
-///	y - The register which will have its data set
+///	x - The register which will have its data set
+///	y - The register whose data will be set to other register
 fn ld_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
-    cpu.registers[y] = cpu.registers[x];
+    cpu.registers[x] = cpu.registers[y];
 }
 
 /// Calculates the OR of the value of register x and the value of register y,
@@ -162,6 +512,7 @@ fn ld_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
 ///	y - The other register which is OR'ed
 fn or_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
     cpu.registers[x] |= cpu.registers[y];
+    reset_flag_after_logic(cpu);
 }
 
 /// Calculates the AND of the value of register x and the value of register y,
@@ -171,6 +522,7 @@ fn or_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
 ///	y - The other register which will be AND'ed
 fn and_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
     cpu.registers[x] &= cpu.registers[y];
+    reset_flag_after_logic(cpu);
 }
 
 /// Calculates the Exclursive OR (XOR) of the value of register x and the value of register y,
@@ -180,108 +532,143 @@ fn and_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
 ///	y - The other register which will be XOR'ed
 fn xor_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
     cpu.registers[x] ^= cpu.registers[y];
+    reset_flag_after_logic(cpu);
+}
+
+/// Sets VF to 0 after a logic instruction, if the quirks say it should be
+///	cpu - The Cpu which executed the logic instruction
+fn reset_flag_after_logic(cpu: &mut Cpu) {
+    if cpu.quirks.logic_resets_vf {
+        cpu.registers[FLAG_REGISTER] = 0;
+    }
 }
 
-/// Calculates the sum of registers x and y then puts that value into register x. If that value
-/// is greater than 255, then VF is set to 1, 0 otherwise
+/// Returns the register whose value a shift instruction shifts, register y if the quirks say
+/// shifts use it, register x otherwise
+///	cpu - The Cpu which is executing the shift instruction
+///	x - The register the shift stores its result in
+///	y - The other register named by the shift
+fn shift_source(cpu: &Cpu, x: usize, y: usize) -> usize {
+    if cpu.quirks.shift_uses_vy {
+        y
+    } else {
+        x
+    }
+}
+
+/// Calculates the sum of registers x and y, wrapping past 255, then puts that value into
+/// register x. If the sum is greater than 255, then VF is set to 1, 0 otherwise
 ///	cpu - The Cpu which we should execute this instruction on
 ///	x - The register which will be set, added to the other register
 ///	y - The other register which will be added
 fn add_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
-    match cpu.registers[x].checked_add(cpu.registers[y]) {
-        Some(z) => {
-            cpu.registers[x] = z;
-            cpu.registers[FLAG_REGISTER] = 0;
-        }
-        None => {
-            cpu.registers[x] = u8::MAX;
-            cpu.registers[FLAG_REGISTER] = 1;
-        }
-    };
+    let (sum, carry) = cpu.registers[x].overflowing_add(cpu.registers[y]);
+    cpu.registers[x] = sum;
+    cpu.registers[FLAG_REGISTER] = carry as u8;
 }
 
-/// Calculates the difference between registers x and y then puts that value into regsiter x. If that value
-/// would result in overflow then VF is set to 0, 1 otherwise
+/// Calculates the difference between registers x and y, wrapping below 0, then puts that value
+/// into regsiter x. If the subtraction borrows then VF is set to 0, 1 otherwise
 ///	cpu - The Cpu which we should execute this instruction on
 ///	x - The register which will be set, the first operand of the subtraction
 ///	y - The register which is the second operand of the subtraction
 fn sub_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
-    match cpu.registers[x].checked_sub(cpu.registers[y]) {
-        Some(z) => {
-            cpu.registers[x] = z;
-            cpu.registers[FLAG_REGISTER] = 1;
-        }
-        None => {
-            cpu.registers[x] = 0;
-            cpu.registers[FLAG_REGISTER] = 0;
-        }
-    }
+    let (difference, borrow) = cpu.registers[x].overflowing_sub(cpu.registers[y]);
+    cpu.registers[x] = difference;
+    cpu.registers[FLAG_REGISTER] = !borrow as u8;
 }
 
-/// Shifts the bits in register vx right once, if the LSB is 1, VF is set to 1, 0 otherwise
+/// Shifts the bits in register vx right once, if the LSB is 1, VF is set to 1, 0 otherwise.
+/// If the quirks say shifts use register y, register y is shifted into register x instead
 ///	cpu - The Cpu which we should execute this instruction on
 ///	x - The register which will be bit-shifted right
-fn shr_vx_vy(cpu: &mut Cpu, x: usize) {
-    if cpu.registers[x] % 2 == 0 {
-        cpu.registers[FLAG_REGISTER] = 0;
-    } else {
-        cpu.registers[FLAG_REGISTER] = 0;
-    }
-    cpu.registers[x] >>= 1;
+///	y - The register which is shifted into register x, if the quirks say so
+fn shr_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
+    let value = cpu.registers[shift_source(cpu, x, y)];
+    cpu.registers[x] = value >> 1;
+    cpu.registers[FLAG_REGISTER] = value & 1;
 }
 
-/// Calculates the difference between registers y and x then puts that value into regsiter x. If that value
-/// would result in overflow, then VF is set to 0, 1 otherwise
+/// Calculates the difference between registers y and x, wrapping below 0, then puts that value
+/// into regsiter x. If the subtraction borrows, then VF is set to 0, 1 otherwise
 ///	cpu - The Cpu which we should execute this instruction on
 ///	x - The register which will be set, the second operand of the subtraction
 ///	y - The register which is the first operand of the subtraction
 fn subn_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
-    match cpu.registers[y].checked_sub(cpu.registers[x]) {
-        Some(z) => {
-            cpu.registers[x] = z;
-            cpu.registers[FLAG_REGISTER] = 1;
-        }
-        None => {
-            cpu.registers[x] = 0;
-            cpu.registers[FLAG_REGISTER] = 0;
-        }
-    }
+    let (difference, borrow) = cpu.registers[y].overflowing_sub(cpu.registers[x]);
+    cpu.registers[x] = difference;
+    cpu.registers[FLAG_REGISTER] = !borrow as u8;
 }
 
-/// Shifts the bits in register vx left once, if the MSB is 1, VF is set to 1, 0 otherwise
+/// Shifts the bits in register vx left once, if the MSB is 1, VF is set to 1, 0 otherwise.
+/// If the quirks say shifts use register y, register y is shifted into register x instead
 ///	cpu - The Cpu which we should execute this instruction on
 ///	x - The register whose data which will be shifted left
-fn shl_vx_vy(cpu: &mut Cpu, x: usize) {
-    if cpu.registers[x] >> 7 == 1 {
-        cpu.registers[FLAG_REGISTER] = 1;
-    } else {
-        cpu.registers[FLAG_REGISTER] = 0;
-    }
-    cpu.registers[x] <<= 1;
+///	y - The register which is shifted into register x, if the quirks say so
+fn shl_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
+    let value = cpu.registers[shift_source(cpu, x, y)];
+    cpu.registers[x] = value << 1;
+    cpu.registers[FLAG_REGISTER] = value >> 7;
 }
 
 /// Skips the next instruction if the value of register x is not equal to register y
 ///	cpu - The Cpu which we should execute this instruction on
 ///	x - The first register which will be checked for inequality
 ///	y - The second register which will be checked for inequality
-fn sne_vx_vy(cpu: &mut Cpu, x: usize, y: usize) {
+fn sne_vx_vy(cpu: &mut Cpu, x: usize, y: usize) -> Result<(), Fault> {
     if cpu.registers[x] != cpu.registers[y] {
-        cpu.pc += 2;
+        skip_next_instruction(cpu)?;
     }
+    Ok(())
 }
 
 ///	Sets the value of the index register to the address passed
 ///	cpu - The Cpu which we should execute this instruction on
 ///	addr - The address which should be placed in the index register
 fn ld_i_addr(cpu: &mut Cpu, addr: u16) {
-    cpu.index_register = addr;
+    cpu.index_register = addr as u32;
 }
 
-///	Jumps to the location: addr + V0
+///	Jumps to the location: addr + V0. If the quirks say the jump uses Vx, the highest nibble of
+/// addr picks the register instead of V0
 ///	cpu - The Cpu which we should execute this instruction on
 /// addr - The address which, summed with register 0's value, will be jumped to
 fn jp_v0_addr(cpu: &mut Cpu, addr: u16) {
-    cpu.pc = (cpu.registers[0] as u16) + addr;
+    let x = if cpu.quirks.jump_uses_vx {
+        (addr >> 8) as usize
+    } else {
+        0
+    };
+    cpu.pc = (cpu.registers[x] as u16) + addr;
+}
+
+/// Sets the foreground colour of zones of the CHIP-8X colour map to the value of register y.
+/// With a nibble of 0, register x holds the first zone column in its low nibble and the number
+/// of extra columns in its high nibble, register x + 1 does the same for blocks of 4 rows.
+/// Otherwise register x and register x + 1 are the pixel position of a zone, which is coloured
+/// for nibble rows
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register holding the horizontal position, x + 1 holds the vertical position
+///	y - The register holding the colour
+///	nibble - The number of rows to colour, or 0 to colour blocks of zones
+fn col_vx_vy_nibble(cpu: &mut Cpu, x: usize, y: usize, nibble: usize) {
+    cpu.screen_dirty = true;
+    let horizontal = cpu.registers[x] as usize;
+    let vertical = cpu.registers[(x + 1) % cpu.registers.len()] as usize;
+    let colour = cpu.registers[y];
+    let Some(colour_map) = &mut cpu.colour_map else {
+        return;
+    };
+    if nibble == 0 {
+        let column = horizontal & 0xf;
+        let row = (vertical & 0xf) * ZONE_BLOCK_ROWS;
+        let columns = column..column + (horizontal >> 4) + 1;
+        let rows = row..row + ((vertical >> 4) + 1) * ZONE_BLOCK_ROWS;
+        colour_map.fill(columns, rows, colour);
+    } else {
+        let column = horizontal / ZONE_WIDTH;
+        colour_map.fill(column..column + 1, vertical..vertical + nibble, colour);
+    }
 }
 
 /// Generates a random byte then AND's it with the byte passed, then sets register x to it
@@ -293,37 +680,360 @@ fn rnd_vx_byte(cpu: &mut Cpu, x: usize, byte: u8) {
 }
 
 ///	Draws the nibble size sprite at (register x, register y) starting at the value in
-/// index_register
+/// index_register. On SUPER-CHIP a nibble of 0 draws a 16x16 sprite instead, in MegaChip mode
+/// the nibble is ignored and a palette sprite of the MegaChip sprite size is drawn
 ///	cpu - The Cpu which we should execute this instruction on
 ///	x - The register which we should get the sprites x coordinate from
 ///	y - The register which we should get the sprites y coordinate from
 ///	nibble - The number of bytes to be read from memory for the sprite
-fn drw_vx_vy_nibble(cpu: &mut Cpu, x: usize, y: usize, nibble: usize) {
-    let starting_addr = cpu.index_register as usize;
-    let x = cpu.registers[x];
-    let mut cur_y = cpu.registers[y] as usize;
-    let mut pixels_changed = false;
-
-    for i in 0..nibble {
-        let cur_byte = cpu.memory[starting_addr + i];
-        let mut cur_x = x as usize;
-
-        for j in 0..u8::BITS as u8 {
-            let pixel_on = get_ith_bit(j, cur_byte).unwrap() == 1;
-            let cur_pixel_val = cpu.graphics[cur_y % GRAPHICS_ROWS][cur_x % GRAPHICS_COLUMNS];
-
-            if cur_pixel_val && !pixel_on {
-                cpu.registers[FLAG_REGISTER] = 1;
-                pixels_changed = true
-            } else if !pixels_changed {
-                cpu.registers[FLAG_REGISTER] = 0;
+fn drw_vx_vy_nibble(cpu: &mut Cpu, x: usize, y: usize, nibble: usize) -> Result<(), Fault> {
+    cpu.screen_dirty = true;
+    if cpu.megachip.as_ref().is_some_and(|mega| mega.is_enabled()) {
+        draw_mega_sprite(cpu, x, y)
+    } else if nibble == 0 && cpu.instruction_set.has_super_chip() {
+        draw_sprite(cpu, x, y, BIG_SPRITE_SIZE, BIG_SPRITE_SIZE)
+    } else {
+        draw_sprite(cpu, x, y, u8::BITS as usize, nibble)
+    }
+}
+
+///	Draws the width x height sprite at (register x, register y) starting at the value in
+/// index_register, each row taking width / 8 bytes. Each selected plane gets its own sprite,
+/// the sprite for the first plane comes first. Sprites are XOR'ed onto the screen, VF is
+/// set to 1 if any pixel was turned off, 0 otherwise. The starting position always wraps, the
+/// quirks pick whether the rest of the sprite wraps or is clipped, and whether the Cpu then
+/// waits for the next frame
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register which we should get the sprites x coordinate from
+///	y - The register which we should get the sprites y coordinate from
+///	width - The number of pixels in each row of the sprite, a multiple of 8
+///	height - The number of rows in the sprite
+fn draw_sprite(
+    cpu: &mut Cpu,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> Result<(), Fault> {
+    let bytes_per_row = width / u8::BITS as usize;
+    let sprite_bytes = height * bytes_per_row;
+    let planes: Vec<u8> = [0b01, 0b10]
+        .into_iter()
+        .filter(|plane| cpu.planes & plane != 0)
+        .collect();
+    let sprites = memory_range(
+        cpu,
+        cpu.index_register as usize,
+        sprite_bytes * planes.len(),
+    )?;
+    let scale = cpu.sprite_scale();
+    let columns = cpu.graphics.width() / scale;
+    let rows = cpu.graphics.height() / scale;
+    let start_x = cpu.registers[x] as usize % columns;
+    let start_y = cpu.registers[y] as usize % rows;
+    let clip = cpu.quirks.clip_sprites;
+    cpu.registers[FLAG_REGISTER] = 0;
+
+    // Each selected plane takes its own sprite, one after the other in memory
+    for (plane, sprite_start) in planes.into_iter().zip(sprites.step_by(sprite_bytes.max(1))) {
+        for row in 0..height {
+            let cur_y = start_y + row;
+            if clip && cur_y >= rows {
+                break;
             }
+            let cur_y = cur_y % rows;
 
-            cpu.graphics[cur_y % GRAPHICS_ROWS][cur_x % GRAPHICS_COLUMNS] =
-                cur_pixel_val ^ pixel_on;
-            cur_x += 1;
+            for column in 0..width {
+                let cur_x = start_x + column;
+                if clip && cur_x >= columns {
+                    break;
+                }
+                let cur_byte = cpu.memory[sprite_start + row * bytes_per_row + column / 8];
+                if get_ith_bit((column % 8) as u8, cur_byte) != Some(1) {
+                    continue;
+                }
+                // Each pixel of the sprite covers scale x scale pixels of the screen
+                let (screen_x, screen_y) = (cur_x % columns * scale, cur_y * scale);
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        if cpu.graphics.toggle(screen_x + dx, screen_y + dy, plane) {
+                            cpu.registers[FLAG_REGISTER] = 1;
+                        }
+                    }
+                }
+            }
         }
-        cur_y += 1;
+    }
+    // The COSMAC VIP timing model has already waited for the vertical blank before the draw
+    if cpu.quirks.display_wait && !cpu.has_vip_timing() {
+        cpu.vblank_wait = true;
+    }
+    Ok(())
+}
+
+///	Draws the MegaChip palette sprite at (register x, register y) starting at the value in
+/// index_register, one byte per pixel. VF is set to 1 if any pixel drawn over had the collision
+/// colour, 0 otherwise
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register which we should get the sprites x coordinate from
+///	y - The register which we should get the sprites y coordinate from
+fn draw_mega_sprite(cpu: &mut Cpu, x: usize, y: usize) -> Result<(), Fault> {
+    let Some(mega) = &cpu.megachip else {
+        return Ok(());
+    };
+    let (width, height) = mega.sprite_size();
+    let sprite = memory_range(cpu, cpu.index_register as usize, width * height)?;
+    let (start_x, start_y) = (cpu.registers[x] as usize, cpu.registers[y] as usize);
+    if let Some(mega) = &mut cpu.megachip {
+        let collision = mega.draw_sprite(start_x, start_y, &cpu.memory[sprite]);
+        cpu.registers[FLAG_REGISTER] = collision as u8;
+    }
+    Ok(())
+}
+
+/// Skips the next instruction if the key with the value of register x is pressed
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register holding the key to check
+fn skp_vx(cpu: &mut Cpu, x: usize) -> Result<(), Fault> {
+    if cpu.keypad.is_pressed(cpu.registers[x]) {
+        skip_next_instruction(cpu)?;
+    }
+    Ok(())
+}
+
+/// Skips the next instruction if the key with the value of register x is not pressed
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register holding the key to check
+fn sknp_vx(cpu: &mut Cpu, x: usize) -> Result<(), Fault> {
+    if !cpu.keypad.is_pressed(cpu.registers[x]) {
+        skip_next_instruction(cpu)?;
+    }
+    Ok(())
+}
+
+/// Sets the index register to the 16 bit address following the instruction, then moves the
+/// program counter past it
+///	cpu - The Cpu which we should execute this instruction on
+fn ld_i_long(cpu: &mut Cpu) -> Result<(), Fault> {
+    let addr = memory_range(cpu, cpu.pc as usize, 2)?;
+    cpu.index_register = (cpu.memory[addr.start] as u32) << 8 | cpu.memory[addr.start + 1] as u32;
+    cpu.pc = advance_pc(cpu, 2)?;
+    Ok(())
+}
+
+/// Selects the bit planes which are drawn, cleared and scrolled
+///	cpu - The Cpu which we should execute this instruction on
+///	planes - The planes to select, bit n for plane n + 1
+fn plane(cpu: &mut Cpu, planes: u8) {
+    cpu.planes = planes & ALL_PLANES;
+}
+
+/// Loads the audio pattern from the 16 bytes of memory starting at the index register
+///	cpu - The Cpu which we should execute this instruction on
+fn audio(cpu: &mut Cpu) -> Result<(), Fault> {
+    let range = memory_range(cpu, cpu.index_register as usize, AUDIO_PATTERN_BYTES)?;
+    let mut pattern = [0; AUDIO_PATTERN_BYTES];
+    pattern.copy_from_slice(&cpu.memory[range]);
+    cpu.audio_pattern = Some(pattern);
+    Ok(())
+}
+
+/// Sets the pitch the audio pattern is played at to the value of register x
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register holding the pitch
+fn pitch_vx(cpu: &mut Cpu, x: usize) {
+    cpu.pitch = cpu.registers[x];
+}
+
+/// Skips the next instruction if the key with the value of register x is pressed on the second
+/// keypad
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register holding the key to check
+fn skp2_vx(cpu: &mut Cpu, x: usize) -> Result<(), Fault> {
+    if cpu.second_keypad.is_pressed(cpu.registers[x]) {
+        skip_next_instruction(cpu)?;
+    }
+    Ok(())
+}
+
+/// Skips the next instruction if the key with the value of register x is not pressed on the
+/// second keypad
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register holding the key to check
+fn sknp2_vx(cpu: &mut Cpu, x: usize) -> Result<(), Fault> {
+    if !cpu.second_keypad.is_pressed(cpu.registers[x]) {
+        skip_next_instruction(cpu)?;
+    }
+    Ok(())
+}
+
+/// Sets register x to the value of the delay timer
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register which will be set
+fn ld_vx_dt(cpu: &mut Cpu, x: usize) {
+    cpu.registers[x] = cpu.timers.delay();
+}
+
+/// Halts execution until a key is pressed and released, then stores the value of the key in
+/// register x. Only keys released after this instruction count
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register which will be set to the pressed key
+fn ld_vx_k(cpu: &mut Cpu, x: usize) {
+    cpu.keypad.clear_released();
+    cpu.key_wait = Some(x);
+}
+
+/// Sets the delay timer to the value of register x
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register whose value the delay timer will be set to
+fn ld_dt_vx(cpu: &mut Cpu, x: usize) {
+    cpu.timers.set_delay(cpu.registers[x]);
+}
+
+/// Halts execution until the delay timer reaches 0
+///	cpu - The Cpu which we should execute this instruction on
+fn wait_dt(cpu: &mut Cpu) {
+    cpu.delay_wait = true;
+}
+
+/// Sets the delay timer to the value of register x, then halts execution until it reaches 0
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register whose value the delay timer will be set to
+fn ld_dt_vx_wait(cpu: &mut Cpu, x: usize) {
+    ld_dt_vx(cpu, x);
+    wait_dt(cpu);
+}
+
+/// Jumps back nn bytes from the start of this instruction
+///	cpu - The Cpu which we should execute this instruction on
+///	nn - The number of bytes to jump back
+fn jp_back_byte(cpu: &mut Cpu, nn: u8) {
+    cpu.pc = cpu.pc.wrapping_sub(2 + nn as u16);
+}
+
+/// Jumps forward nn bytes from the start of this instruction
+///	cpu - The Cpu which we should execute this instruction on
+///	nn - The number of bytes to jump forward
+fn jp_fwd_byte(cpu: &mut Cpu, nn: u8) {
+    cpu.pc = cpu.pc.wrapping_sub(2).wrapping_add(nn as u16);
+}
+
+/// Skips forward the number of bytes in register x
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register holding the number of bytes to skip
+fn skip_vx(cpu: &mut Cpu, x: usize) {
+    cpu.pc = cpu.pc.wrapping_add(cpu.registers[x] as u16);
+}
+
+/// Writes the value of register x to the output port
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register whose value is written
+fn out_vx(cpu: &mut Cpu, x: usize) {
+    cpu.output_port = cpu.registers[x];
+}
+
+/// Sets register x to the byte on the input port, or 0 if there is none
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register which will be set
+fn in_vx(cpu: &mut Cpu, x: usize) {
+    cpu.registers[x] = cpu.input_port.take().unwrap_or(0);
+}
+
+/// Sets register x to the byte on the input port, running this instruction again until there
+/// is one
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register which will be set
+fn in_wait_vx(cpu: &mut Cpu, x: usize) {
+    match cpu.input_port.take() {
+        Some(value) => cpu.registers[x] = value,
+        None => cpu.pc -= 2,
+    }
+}
+
+/// Sets the sound timer to the value of register x
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register whose value the sound timer will be set to
+fn ld_st_vx(cpu: &mut Cpu, x: usize) {
+    cpu.timers.set_sound(cpu.registers[x]);
+}
+
+/// Adds the value of register x to the index register
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register whose value is added to the index register
+fn add_i_vx(cpu: &mut Cpu, x: usize) {
+    cpu.index_register = cpu.index_register.wrapping_add(cpu.registers[x] as u32);
+}
+
+/// Sets the index register to the location of the font sprite for the digit in register x
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register holding the digit, only the lowest nibble is used
+fn ld_f_vx(cpu: &mut Cpu, x: usize) {
+    let digit = (cpu.registers[x] & 0xf) as usize;
+    cpu.index_register = (cpu.font_address as usize + digit * FONT_SPRITE_BYTES) as u32;
+}
+
+/// Sets the index register to the location of the big font sprite for the digit in register x
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register holding the digit, only the lowest nibble is used
+fn ld_hf_vx(cpu: &mut Cpu, x: usize) {
+    let digit = (cpu.registers[x] & 0xf) as usize;
+    cpu.index_register = (cpu.big_font_address as usize + digit * BIG_FONT_SPRITE_BYTES) as u32;
+}
+
+/// Stores the binary-coded decimal representation of register x in memory, the hundreds digit
+/// at the index register, the tens at index register + 1 and the ones at index register + 2
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The register whose value will be stored
+fn ld_b_vx(cpu: &mut Cpu, x: usize) -> Result<(), Fault> {
+    let value = cpu.registers[x];
+    let digits = memory_range(cpu, cpu.index_register as usize, 3)?;
+    cpu.memory[digits].copy_from_slice(&[value / 100, value / 10 % 10, value % 10]);
+    Ok(())
+}
+
+/// Stores registers V0 through register x in memory, starting at the index register
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The last register which will be stored
+fn ld_i_vx(cpu: &mut Cpu, x: usize) -> Result<(), Fault> {
+    let range = memory_range(cpu, cpu.index_register as usize, x + 1)?;
+    cpu.memory[range].copy_from_slice(&cpu.registers[..=x]);
+    increment_i_after_load_store(cpu, x);
+    Ok(())
+}
+
+/// Reads registers V0 through register x from memory, starting at the index register
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The last register which will be read
+fn ld_vx_i(cpu: &mut Cpu, x: usize) -> Result<(), Fault> {
+    let range = memory_range(cpu, cpu.index_register as usize, x + 1)?;
+    cpu.registers[..=x].copy_from_slice(&cpu.memory[range]);
+    increment_i_after_load_store(cpu, x);
+    Ok(())
+}
+
+/// Stores registers V0 through register x in the RPL user flags
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The last register which will be stored
+fn ld_r_vx(cpu: &mut Cpu, x: usize) {
+    let x = x.min(NUM_RPL_FLAGS - 1);
+    cpu.rpl_flags[..=x].copy_from_slice(&cpu.registers[..=x]);
+}
+
+/// Reads registers V0 through register x from the RPL user flags
+///	cpu - The Cpu which we should execute this instruction on
+///	x - The last register which will be read
+fn ld_vx_r(cpu: &mut Cpu, x: usize) {
+    let x = x.min(NUM_RPL_FLAGS - 1);
+    cpu.registers[..=x].copy_from_slice(&cpu.rpl_flags[..=x]);
+}
+
+/// Moves the index register past the registers stored or read by Fx55 and Fx65, if the
+/// quirks say it should be
+///	cpu - The Cpu which executed the load or store
+///	x - The last register which was stored or read
+fn increment_i_after_load_store(cpu: &mut Cpu, x: usize) {
+    if cpu.quirks.load_store_increments_i {
+        cpu.index_register = cpu.index_register.wrapping_add(x as u32 + 1);
     }
 }
 
@@ -336,7 +1046,792 @@ fn get_ith_bit(bit_num: u8, byte: u8) -> Option<u8> {
         return None;
     }
     let single_bit_mask = 0b1000_0000 >> bit_num;
-    let res = byte & single_bit_mask;
+    let res = (byte & single_bit_mask) >> (7 - bit_num);
     assert!(res == 1 || res == 0, "The result was neither 1 or 0");
     Some(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::instruction::decode_for;
+    use crate::cpu::{
+        CpuConfig, InstructionSet, Platform, BIG_FONT_SET_START, CALL_STACK_SIZE, DEFAULT_PLANE,
+        FONT_SET_START, GRAPHICS_COLUMNS, GRAPHICS_ROWS, HIRES_GRAPHICS_COLUMNS,
+        HIRES_GRAPHICS_ROWS, MEM_SIZE,
+    };
+    use crate::display::RgbaImage;
+
+    /// Executes the op code passed, returning the fault if it could not be executed
+    ///	cpu - The Cpu to execute the operation on
+    ///	op_code - The opcode to execute
+    fn exec_opcode(cpu: &mut Cpu, op_code: u16) -> Result<(), Fault> {
+        execute(cpu, decode_for(op_code, cpu.instruction_set))
+    }
+
+    #[test]
+    fn ld_vx_dt_reads_delay_timer() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.timers.set_delay(0x42);
+        exec_opcode(&mut cpu, 0xf307).unwrap();
+        assert_eq!(cpu.registers[3], 0x42);
+    }
+
+    #[test]
+    fn ld_vx_vy_copies_vy_into_vx() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[2] = 0x42;
+        exec_opcode(&mut cpu, 0x8120).unwrap();
+        assert_eq!(cpu.registers[1], 0x42);
+    }
+
+    #[test]
+    fn add_vx_vy_wraps_and_sets_carry() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[1] = 0xf0;
+        cpu.registers[2] = 0x20;
+        exec_opcode(&mut cpu, 0x8124).unwrap();
+        assert_eq!(cpu.registers[1], 0x10);
+        assert_eq!(cpu.registers[FLAG_REGISTER], 1);
+    }
+
+    #[test]
+    fn sub_and_subn_wrap_and_set_not_borrow() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[1] = 0x10;
+        cpu.registers[2] = 0x20;
+        exec_opcode(&mut cpu, 0x8125).unwrap();
+        assert_eq!(cpu.registers[1], 0xf0);
+        assert_eq!(cpu.registers[FLAG_REGISTER], 0);
+
+        cpu.registers[1] = 0x10;
+        exec_opcode(&mut cpu, 0x8127).unwrap();
+        assert_eq!(cpu.registers[1], 0x10);
+        assert_eq!(cpu.registers[FLAG_REGISTER], 1);
+    }
+
+    #[test]
+    fn shr_vx_vy_sets_flag_to_lsb() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[1] = 0b0000_0011;
+        exec_opcode(&mut cpu, 0x8106).unwrap();
+        assert_eq!(cpu.registers[1], 0b0000_0001);
+        assert_eq!(cpu.registers[FLAG_REGISTER], 1);
+    }
+
+    #[test]
+    fn drw_xors_sprite_and_sets_collision() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.index_register = 0x300;
+        cpu.memory[0x300] = 0b1100_0000;
+        cpu.registers[0] = 63;
+        cpu.registers[1] = 31;
+        exec_opcode(&mut cpu, 0xd011).unwrap();
+        assert!(cpu.graphics.get(63, 31));
+        assert!(cpu.graphics.get(0, 31));
+        assert_eq!(cpu.registers[FLAG_REGISTER], 0);
+
+        exec_opcode(&mut cpu, 0xd011).unwrap();
+        assert!(!cpu.graphics.get(63, 31));
+        assert!(!cpu.graphics.get(0, 31));
+        assert_eq!(cpu.registers[FLAG_REGISTER], 1);
+    }
+
+    #[test]
+    fn logic_resets_vf_quirk() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[FLAG_REGISTER] = 5;
+        exec_opcode(&mut cpu, 0x8011).unwrap();
+        assert_eq!(cpu.registers[FLAG_REGISTER], 5);
+
+        cpu.quirks.logic_resets_vf = true;
+        exec_opcode(&mut cpu, 0x8012).unwrap();
+        assert_eq!(cpu.registers[FLAG_REGISTER], 0);
+    }
+
+    #[test]
+    fn shift_uses_vy_quirk() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[1] = 0b1000_0001;
+        cpu.registers[2] = 0b0100_0000;
+        exec_opcode(&mut cpu, 0x812e).unwrap();
+        assert_eq!(cpu.registers[1], 0b0000_0010);
+        assert_eq!(cpu.registers[FLAG_REGISTER], 1);
+
+        cpu.quirks.shift_uses_vy = true;
+        exec_opcode(&mut cpu, 0x812e).unwrap();
+        assert_eq!(cpu.registers[1], 0b1000_0000);
+        assert_eq!(cpu.registers[FLAG_REGISTER], 0);
+    }
+
+    #[test]
+    fn jump_uses_vx_quirk() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[0] = 0x10;
+        cpu.registers[3] = 0x20;
+        exec_opcode(&mut cpu, 0xb300).unwrap();
+        assert_eq!(cpu.pc, 0x310);
+
+        cpu.quirks.jump_uses_vx = true;
+        exec_opcode(&mut cpu, 0xb300).unwrap();
+        assert_eq!(cpu.pc, 0x320);
+    }
+
+    #[test]
+    fn load_store_increments_i_quirk() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.quirks.load_store_increments_i = true;
+        cpu.index_register = 0x300;
+        exec_opcode(&mut cpu, 0xf255).unwrap();
+        assert_eq!(cpu.index_register, 0x303);
+        exec_opcode(&mut cpu, 0xf065).unwrap();
+        assert_eq!(cpu.index_register, 0x304);
+    }
+
+    #[test]
+    fn clip_sprites_quirk() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.quirks.clip_sprites = true;
+        cpu.index_register = 0x300;
+        cpu.memory[0x300..0x302].copy_from_slice(&[0xff, 0xff]);
+        cpu.registers[0] = 60;
+        cpu.registers[1] = 31;
+        exec_opcode(&mut cpu, 0xd012).unwrap();
+        assert!(cpu.graphics.get(63, 31));
+        assert!(!cpu.graphics.get(0, 31));
+        assert!(!cpu.graphics.get(60, 0));
+    }
+
+    #[test]
+    fn display_wait_quirk() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.quirks.display_wait = true;
+        cpu.load_rom_bytes(&[0xd0, 0x01, 0x60, 0x01]).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x202);
+
+        cpu.tick_timers();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers[0], 1);
+    }
+
+    #[test]
+    fn high_and_low_switch_resolution() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.instruction_set = InstructionSet::SuperChip;
+        exec_opcode(&mut cpu, 0x00ff).unwrap();
+        assert_eq!(cpu.graphics.width(), HIRES_GRAPHICS_COLUMNS);
+        assert_eq!(cpu.graphics.height(), HIRES_GRAPHICS_ROWS);
+        exec_opcode(&mut cpu, 0x00fe).unwrap();
+        assert_eq!(cpu.graphics.width(), GRAPHICS_COLUMNS);
+        assert_eq!(cpu.graphics.height(), GRAPHICS_ROWS);
+    }
+
+    #[test]
+    fn resolution_changes_clear_the_screen_unless_the_quirk_keeps_it() {
+        let mut cpu = Cpu::with_platform(Platform::SchipModern);
+        cpu.graphics.set(1, 1, DEFAULT_PLANE);
+        exec_opcode(&mut cpu, 0x00ff).unwrap();
+        assert!(cpu.graphics.rows().flatten().all(|&planes| planes == 0));
+
+        let mut cpu = Cpu::with_platform(Platform::SchipLegacy);
+        assert!(cpu.quirks.resolution_change_keeps_screen);
+        cpu.index_register = 0x300;
+        cpu.memory[0x300] = 0x80;
+        cpu.registers[0] = 1;
+        exec_opcode(&mut cpu, 0xd001).unwrap();
+        exec_opcode(&mut cpu, 0x00ff).unwrap();
+        assert_eq!(
+            cpu.resolution(),
+            (HIRES_GRAPHICS_COLUMNS, HIRES_GRAPHICS_ROWS)
+        );
+        assert!(cpu.graphics.get(2, 2) && cpu.graphics.get(3, 3));
+        exec_opcode(&mut cpu, 0x00fe).unwrap();
+        assert!(cpu.graphics.get(2, 2) && cpu.graphics.get(3, 3));
+    }
+
+    #[test]
+    fn lores_scrolls_by_half_a_pixel_with_the_quirk() {
+        let mut cpu = Cpu::with_platform(Platform::SchipModern);
+        cpu.graphics.set(0, 0, DEFAULT_PLANE);
+        exec_opcode(&mut cpu, 0x00c1).unwrap();
+        assert!(!cpu.graphics.get(0, 0) && cpu.graphics.get(0, 1));
+
+        let mut cpu = Cpu::with_platform(Platform::SchipLegacy);
+        assert!(cpu.quirks.half_pixel_lores_scroll);
+        cpu.index_register = 0x300;
+        cpu.memory[0x300] = 0x80;
+        exec_opcode(&mut cpu, 0xd001).unwrap();
+        // The sprite pixel covers the 2x2 screen pixels in the top left corner
+        assert_eq!(
+            cpu.resolution(),
+            (HIRES_GRAPHICS_COLUMNS, HIRES_GRAPHICS_ROWS)
+        );
+        exec_opcode(&mut cpu, 0x00c1).unwrap();
+        assert!(!cpu.graphics.get(0, 0));
+        assert!(cpu.graphics.get(0, 1) && cpu.graphics.get(0, 2));
+        assert!(!cpu.graphics.get(0, 3));
+        exec_opcode(&mut cpu, 0x00fb).unwrap();
+        assert!(cpu.graphics.get(4, 1) && cpu.graphics.get(5, 1));
+        assert!(!cpu.graphics.get(3, 1) && !cpu.graphics.get(6, 1));
+    }
+
+    #[test]
+    fn drw_big_sprite_in_hires() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.instruction_set = InstructionSet::SuperChip;
+        exec_opcode(&mut cpu, 0x00ff).unwrap();
+        cpu.index_register = 0x300;
+        cpu.memory[0x300..0x320].fill(0xff);
+        cpu.registers[0] = 100;
+        cpu.registers[1] = 40;
+        exec_opcode(&mut cpu, 0xd010).unwrap();
+        assert!(cpu.graphics.get(100, 40));
+        assert!(cpu.graphics.get(115, 55));
+        assert!(!cpu.graphics.get(116, 40));
+        assert_eq!(cpu.registers[FLAG_REGISTER], 0);
+    }
+
+    #[test]
+    fn drw_zero_rows_without_super_chip() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.memory[0..32].fill(0xff);
+        exec_opcode(&mut cpu, 0xd010).unwrap();
+        assert!(!cpu.graphics.get(0, 0));
+    }
+
+    #[test]
+    fn scrolls_move_the_screen() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.instruction_set = InstructionSet::SuperChip;
+        cpu.graphics.set(10, 10, DEFAULT_PLANE);
+        exec_opcode(&mut cpu, 0x00c2).unwrap();
+        assert!(cpu.graphics.get(10, 12));
+        exec_opcode(&mut cpu, 0x00fb).unwrap();
+        assert!(cpu.graphics.get(14, 12));
+        exec_opcode(&mut cpu, 0x00fc).unwrap();
+        exec_opcode(&mut cpu, 0x00fc).unwrap();
+        assert!(cpu.graphics.get(6, 12));
+    }
+
+    #[test]
+    fn exit_stops_the_cpu() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.instruction_set = InstructionSet::SuperChip;
+        cpu.load_rom_bytes(&[0x00, 0xfd, 0x60, 0x01]).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert!(cpu.has_exited());
+        assert_eq!(cpu.registers[0], 0);
+    }
+
+    #[test]
+    fn ld_hf_vx_points_at_big_font_sprite() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.instruction_set = InstructionSet::SuperChip;
+        cpu.registers[2] = 8;
+        exec_opcode(&mut cpu, 0xf230).unwrap();
+        let addr = cpu.index_register as usize;
+        assert_eq!(addr, BIG_FONT_SET_START + 8 * BIG_FONT_SPRITE_BYTES);
+        assert_eq!(cpu.memory[addr..addr + 2], [0xff, 0xff]);
+    }
+
+    #[test]
+    fn rpl_flags_save_and_restore_registers() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.instruction_set = InstructionSet::SuperChip;
+        cpu.registers[..3].copy_from_slice(&[1, 2, 3]);
+        exec_opcode(&mut cpu, 0xf275).unwrap();
+        cpu.registers = [0; 16];
+        exec_opcode(&mut cpu, 0xf185).unwrap();
+        assert_eq!(cpu.registers[..3], [1, 2, 0]);
+        assert_eq!(cpu.rpl_flags[..3], [1, 2, 3]);
+    }
+
+    #[test]
+    fn call_and_ret_use_the_stack() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.pc = 0x202;
+        exec_opcode(&mut cpu, 0x2400).unwrap();
+        assert_eq!(cpu.pc, 0x400);
+        exec_opcode(&mut cpu, 0x00ee).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+        assert!(cpu.stack.is_empty());
+    }
+
+    #[test]
+    fn call_faults_when_stack_full() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        for _ in 0..CALL_STACK_SIZE {
+            exec_opcode(&mut cpu, 0x2400).unwrap();
+        }
+        assert_eq!(exec_opcode(&mut cpu, 0x2400), Err(Fault::StackOverflow));
+    }
+
+    #[test]
+    fn call_stack_depth_comes_from_the_config() {
+        let mut cpu = Cpu::new(CpuConfig {
+            stack_depth: Some(12),
+            ..CpuConfig::default()
+        });
+        for _ in 0..12 {
+            exec_opcode(&mut cpu, 0x2400).unwrap();
+        }
+        assert_eq!(exec_opcode(&mut cpu, 0x2400), Err(Fault::StackOverflow));
+
+        let mut cpu = Cpu::new(CpuConfig {
+            stack_depth: None,
+            ..CpuConfig::default()
+        });
+        for _ in 0..1000 {
+            exec_opcode(&mut cpu, 0x2400).unwrap();
+        }
+        assert_eq!(cpu.stack().len(), 1000);
+    }
+
+    #[test]
+    fn ret_faults_when_stack_empty() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        assert_eq!(exec_opcode(&mut cpu, 0x00ee), Err(Fault::StackUnderflow));
+    }
+
+    #[test]
+    fn drw_faults_when_sprite_past_end_of_memory() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.index_register = 0xffe;
+        assert_eq!(
+            exec_opcode(&mut cpu, 0xd014),
+            Err(Fault::MemoryOutOfBounds { addr: MEM_SIZE })
+        );
+    }
+
+    #[test]
+    fn unknown_opcode_faults() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        assert_eq!(exec_opcode(&mut cpu, 0xffff), Err(Fault::UnknownOpcode));
+    }
+
+    #[test]
+    fn skp_vx_skips_when_key_pressed() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[1] = 0xb;
+        cpu.press_key(0xb);
+        exec_opcode(&mut cpu, 0xe19e).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+        cpu.release_key(0xb);
+        exec_opcode(&mut cpu, 0xe19e).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn sknp_vx_skips_when_key_not_pressed() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[1] = 0x3;
+        exec_opcode(&mut cpu, 0xe1a1).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+        cpu.press_key(0x3);
+        exec_opcode(&mut cpu, 0xe1a1).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn ld_vx_k_waits_for_press_and_release() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.memory[0x200..0x204].copy_from_slice(&[0xf2, 0x0a, 0x60, 0x01]);
+        cpu.timers.set_delay(10);
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        cpu.tick_timers();
+        assert!(cpu.is_waiting_for_key());
+        assert_eq!(cpu.pc, 0x202);
+        assert_eq!(cpu.timers.delay(), 9);
+
+        cpu.press_key(0x7);
+        cpu.cycle().unwrap();
+        assert!(cpu.is_waiting_for_key());
+
+        cpu.release_key(0x7);
+        cpu.cycle().unwrap();
+        assert!(!cpu.is_waiting_for_key());
+        assert_eq!(cpu.registers[2], 0x7);
+        assert_eq!(cpu.pc, 0x202);
+
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers[0], 0x1);
+    }
+
+    #[test]
+    fn ld_dt_vx_sets_delay_timer() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[5] = 0x10;
+        exec_opcode(&mut cpu, 0xf515).unwrap();
+        assert_eq!(cpu.timers.delay(), 0x10);
+    }
+
+    #[test]
+    fn ld_st_vx_sets_sound_timer() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[0xa] = 0x20;
+        exec_opcode(&mut cpu, 0xfa18).unwrap();
+        assert_eq!(cpu.timers.sound(), 0x20);
+    }
+
+    #[test]
+    fn add_i_vx_adds_to_index_register() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.index_register = 0x300;
+        cpu.registers[1] = 0x0f;
+        exec_opcode(&mut cpu, 0xf11e).unwrap();
+        assert_eq!(cpu.index_register, 0x30f);
+    }
+
+    #[test]
+    fn ld_f_vx_points_at_font_sprite() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[2] = 0xa;
+        exec_opcode(&mut cpu, 0xf229).unwrap();
+        let addr = cpu.index_register as usize;
+        assert_eq!(addr, FONT_SET_START + 0xa * FONT_SPRITE_BYTES);
+        assert_eq!(cpu.memory[addr..addr + 5], [0xf0, 0x90, 0xf0, 0x90, 0x90]);
+    }
+
+    #[test]
+    fn ld_b_vx_stores_bcd() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.index_register = 0x300;
+        cpu.registers[4] = 254;
+        exec_opcode(&mut cpu, 0xf433).unwrap();
+        assert_eq!(cpu.memory[0x300..0x303], [2, 5, 4]);
+    }
+
+    #[test]
+    fn ld_i_vx_stores_registers() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.index_register = 0x300;
+        cpu.registers[..4].copy_from_slice(&[1, 2, 3, 4]);
+        exec_opcode(&mut cpu, 0xf255).unwrap();
+        assert_eq!(cpu.memory[0x300..0x304], [1, 2, 3, 0]);
+        assert_eq!(cpu.index_register, 0x300);
+    }
+
+    #[test]
+    fn ld_vx_i_reads_registers() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.index_register = 0x300;
+        cpu.memory[0x300..0x304].copy_from_slice(&[9, 8, 7, 6]);
+        exec_opcode(&mut cpu, 0xf265).unwrap();
+        assert_eq!(cpu.registers[..4], [9, 8, 7, 0]);
+        assert_eq!(cpu.index_register, 0x300);
+    }
+
+    fn xo_chip_cpu() -> Cpu {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_instruction_set(InstructionSet::XoChip);
+        cpu
+    }
+
+    #[test]
+    fn ld_i_long_reads_the_next_two_bytes() {
+        let mut cpu = xo_chip_cpu();
+        cpu.memory[0x200..0x202].copy_from_slice(&[0xe1, 0x23]);
+        exec_opcode(&mut cpu, 0xf000).unwrap();
+        assert_eq!(cpu.index_register, 0xe123);
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn audio_loads_the_pattern_and_pitch_sets_its_rate() {
+        let mut cpu = xo_chip_cpu();
+        assert_eq!(cpu.audio_pattern(), None);
+        cpu.index_register = 0x300;
+        cpu.memory[0x300..0x310].copy_from_slice(&[0xf0; AUDIO_PATTERN_BYTES]);
+        exec_opcode(&mut cpu, 0xf002).unwrap();
+        assert_eq!(cpu.audio_pattern(), Some(&[0xf0; AUDIO_PATTERN_BYTES]));
+        assert_eq!(cpu.index_register, 0x300);
+
+        cpu.registers[5] = 0x70;
+        exec_opcode(&mut cpu, 0xf53a).unwrap();
+        assert_eq!(cpu.pitch(), 0x70);
+    }
+
+    #[test]
+    fn skips_step_over_long_instructions() {
+        let mut cpu = xo_chip_cpu();
+        cpu.memory[0x200..0x202].copy_from_slice(&[0xf0, 0x00]);
+        exec_opcode(&mut cpu, 0x3000).unwrap();
+        assert_eq!(cpu.pc, 0x204);
+
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.memory[0x200..0x202].copy_from_slice(&[0xf0, 0x00]);
+        exec_opcode(&mut cpu, 0x3000).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn long_instructions_at_the_end_of_64k_fault() {
+        let mut cpu = xo_chip_cpu();
+        cpu.pc = 0xfffe;
+        cpu.memory[0xfffe..].copy_from_slice(&[0xe1, 0x23]);
+        assert_eq!(
+            exec_opcode(&mut cpu, 0xf000),
+            Err(Fault::MemoryOutOfBounds { addr: 0x10000 })
+        );
+        assert_eq!(cpu.pc, 0xfffe);
+
+        cpu.pc = 0xfffe;
+        assert_eq!(
+            exec_opcode(&mut cpu, 0x3000),
+            Err(Fault::MemoryOutOfBounds { addr: 0x10000 })
+        );
+        cpu.pc = 0xfffc;
+        cpu.memory[0xfffc..0xfffe].copy_from_slice(&[0xf0, 0x00]);
+        assert_eq!(
+            exec_opcode(&mut cpu, 0x3000),
+            Err(Fault::MemoryOutOfBounds { addr: 0x10000 })
+        );
+        assert_eq!(cpu.pc, 0xfffc);
+    }
+
+    #[test]
+    fn ld_i_vx_vy_stores_range_in_either_order() {
+        let mut cpu = xo_chip_cpu();
+        cpu.index_register = 0x300;
+        cpu.registers[..4].copy_from_slice(&[1, 2, 3, 4]);
+        exec_opcode(&mut cpu, 0x5132).unwrap();
+        assert_eq!(cpu.memory[0x300..0x304], [2, 3, 4, 0]);
+        exec_opcode(&mut cpu, 0x5312).unwrap();
+        assert_eq!(cpu.memory[0x300..0x304], [4, 3, 2, 0]);
+        assert_eq!(cpu.index_register, 0x300);
+    }
+
+    #[test]
+    fn ld_vx_vy_i_reads_range_in_either_order() {
+        let mut cpu = xo_chip_cpu();
+        cpu.index_register = 0x300;
+        cpu.memory[0x300..0x303].copy_from_slice(&[7, 8, 9]);
+        exec_opcode(&mut cpu, 0x5243).unwrap();
+        assert_eq!(cpu.registers[2..5], [7, 8, 9]);
+        exec_opcode(&mut cpu, 0x5423).unwrap();
+        assert_eq!(cpu.registers[2..5], [9, 8, 7]);
+    }
+
+    #[test]
+    fn drw_draws_a_sprite_per_selected_plane() {
+        let mut cpu = xo_chip_cpu();
+        cpu.index_register = 0x300;
+        cpu.memory[0x300..0x302].copy_from_slice(&[0b1000_0000, 0b1100_0000]);
+        exec_opcode(&mut cpu, 0xf301).unwrap();
+        exec_opcode(&mut cpu, 0xd011).unwrap();
+        assert_eq!(cpu.graphics.planes(0, 0), 0b11);
+        assert_eq!(cpu.graphics.planes(1, 0), 0b10);
+
+        exec_opcode(&mut cpu, 0xf201).unwrap();
+        exec_opcode(&mut cpu, 0x00e0).unwrap();
+        assert_eq!(cpu.graphics.planes(0, 0), 0b01);
+        assert_eq!(cpu.graphics.planes(1, 0), 0);
+    }
+
+    #[test]
+    fn scu_scrolls_selected_planes_up() {
+        let mut cpu = xo_chip_cpu();
+        cpu.graphics.set(3, 5, 0b11);
+        exec_opcode(&mut cpu, 0x00d2).unwrap();
+        assert_eq!(cpu.graphics.planes(3, 5), 0b10);
+        assert_eq!(cpu.graphics.planes(3, 3), 0b01);
+    }
+
+    #[test]
+    fn add_nibbles_wraps_each_nibble_at_8() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_instruction_set(InstructionSet::Chip8X);
+        cpu.registers[1] = 0x56;
+        cpu.registers[2] = 0x34;
+        exec_opcode(&mut cpu, 0x5121).unwrap();
+        assert_eq!(cpu.registers[1], 0x02);
+    }
+
+    #[test]
+    fn col_colours_zones() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_instruction_set(InstructionSet::Chip8X);
+        cpu.registers[2] = 0x12;
+        cpu.registers[3] = 0x01;
+        cpu.registers[4] = 6;
+        exec_opcode(&mut cpu, 0xb240).unwrap();
+        let colour_map = cpu.colour_map().unwrap();
+        assert_eq!(colour_map.foreground(16, 4), 6);
+        assert_eq!(colour_map.foreground(31, 7), 6);
+        assert_ne!(colour_map.foreground(32, 4), 6);
+        assert_ne!(colour_map.foreground(16, 8), 6);
+
+        cpu.registers[2] = 40;
+        cpu.registers[3] = 20;
+        cpu.registers[4] = 3;
+        exec_opcode(&mut cpu, 0xb242).unwrap();
+        let colour_map = cpu.colour_map().unwrap();
+        assert_eq!(colour_map.foreground(40, 21), 3);
+        assert_ne!(colour_map.foreground(40, 22), 3);
+    }
+
+    #[test]
+    fn skp2_reads_the_second_keypad() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_instruction_set(InstructionSet::Chip8X);
+        cpu.registers[1] = 7;
+        cpu.press_key(7);
+        exec_opcode(&mut cpu, 0xe1f2).unwrap();
+        assert_eq!(cpu.pc, 0x200);
+        cpu.press_second_key(7);
+        exec_opcode(&mut cpu, 0xe1f2).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    fn mega_chip_cpu() -> Cpu {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_instruction_set(InstructionSet::MegaChip);
+        exec_opcode(&mut cpu, 0x0011).unwrap();
+        cpu
+    }
+
+    #[test]
+    fn ld_i_long_byte_sets_a_24_bit_address() {
+        let mut cpu = mega_chip_cpu();
+        cpu.memory[0x200..0x202].copy_from_slice(&[0x34, 0x56]);
+        exec_opcode(&mut cpu, 0x0112).unwrap();
+        assert_eq!(cpu.index_register, 0x12_3456);
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn mega_drw_draws_palette_sprites_shown_by_cls() {
+        let mut cpu = mega_chip_cpu();
+        assert_eq!(cpu.resolution(), (256, 192));
+        cpu.index_register = 0x300;
+        cpu.memory[0x300..0x304].copy_from_slice(&[0xff, 0x12, 0x34, 0x56]);
+        exec_opcode(&mut cpu, 0x0201).unwrap();
+        exec_opcode(&mut cpu, 0x0302).unwrap();
+        exec_opcode(&mut cpu, 0x0401).unwrap();
+        exec_opcode(&mut cpu, 0x0901).unwrap();
+        cpu.memory[0x310..0x312].copy_from_slice(&[1, 0]);
+        cpu.index_register = 0x310;
+        cpu.registers[0] = 10;
+        cpu.registers[1] = 20;
+        exec_opcode(&mut cpu, 0xd010).unwrap();
+        assert_eq!(cpu.registers[FLAG_REGISTER], 0);
+        exec_opcode(&mut cpu, 0xd010).unwrap();
+        assert_eq!(cpu.registers[FLAG_REGISTER], 1);
+
+        exec_opcode(&mut cpu, 0x00e0).unwrap();
+        let mut image = RgbaImage::new();
+        cpu.present(&mut image).unwrap();
+        assert_eq!((image.width(), image.height()), (256, 192));
+        assert_eq!(image.pixel(10, 20), [0x12, 0x34, 0x56, 0xff]);
+        assert_eq!(image.pixel(11, 20), [0, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn digi_snd_reads_the_sound_header() {
+        let mut cpu = mega_chip_cpu();
+        cpu.index_register = 0x300;
+        cpu.memory[0x300..0x306].copy_from_slice(&[0x1f, 0x40, 0, 0, 4, 0]);
+        exec_opcode(&mut cpu, 0x0601).unwrap();
+        let sound = cpu.megachip().unwrap().sound().unwrap();
+        assert_eq!(sound.start, 0x306);
+        assert_eq!(sound.len, 4);
+        assert_eq!(sound.sample_rate, 8000);
+        assert!(!sound.looping);
+
+        exec_opcode(&mut cpu, 0x0700).unwrap();
+        assert_eq!(cpu.megachip().unwrap().sound(), None);
+    }
+
+    #[test]
+    fn blend_faults_on_unknown_modes() {
+        let mut cpu = mega_chip_cpu();
+        assert_eq!(exec_opcode(&mut cpu, 0x0806), Err(Fault::UnknownOpcode));
+    }
+
+    #[test]
+    fn chip8e_jumps_are_relative_to_the_instruction() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_instruction_set(InstructionSet::Chip8E);
+        cpu.pc = 0x212;
+        exec_opcode(&mut cpu, 0xbb10).unwrap();
+        assert_eq!(cpu.pc, 0x200);
+        exec_opcode(&mut cpu, 0xbf10).unwrap();
+        assert_eq!(cpu.pc, 0x20e);
+        cpu.registers[3] = 6;
+        exec_opcode(&mut cpu, 0xf31b).unwrap();
+        assert_eq!(cpu.pc, 0x214);
+    }
+
+    #[test]
+    fn chip8e_stores_registers_and_moves_i() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_instruction_set(InstructionSet::Chip8E);
+        cpu.index_register = 0x300;
+        cpu.registers[1..4].copy_from_slice(&[1, 2, 3]);
+        exec_opcode(&mut cpu, 0x5132).unwrap();
+        assert_eq!(cpu.memory[0x300..0x303], [1, 2, 3]);
+        assert_eq!(cpu.index_register, 0x303);
+    }
+
+    #[test]
+    fn chip8e_waits_for_the_delay_timer() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_instruction_set(InstructionSet::Chip8E);
+        cpu.registers[0] = 2;
+        cpu.load_rom_bytes(&[0xf0, 0x4f, 0x61, 0x01]).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        cpu.tick_timers();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers[1], 0);
+        cpu.tick_timers();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers[1], 1);
+    }
+
+    #[test]
+    fn chip8i_ports_read_and_write_bytes() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.set_instruction_set(InstructionSet::Chip8I);
+        cpu.registers[2] = 0x42;
+        exec_opcode(&mut cpu, 0xf2f8).unwrap();
+        assert_eq!(cpu.output_port(), 0x42);
+
+        cpu.pc = 0x202;
+        exec_opcode(&mut cpu, 0xf3fb).unwrap();
+        assert_eq!(cpu.pc, 0x200);
+        cpu.set_input_port(0x17);
+        exec_opcode(&mut cpu, 0xf3fb).unwrap();
+        assert_eq!(cpu.registers[3], 0x17);
+    }
+
+    #[test]
+    fn ret_returns_to_the_caller() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        let start = cpu.pc;
+        exec_opcode(&mut cpu, 0x2400).unwrap();
+        exec_opcode(&mut cpu, 0x00ee).unwrap();
+        assert_eq!(cpu.pc, start);
+    }
+
+    #[test]
+    fn add_vx_byte_wraps_without_touching_vf() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.registers[0] = 0xff;
+        exec_opcode(&mut cpu, 0x7002).unwrap();
+        assert_eq!(cpu.registers[0], 1);
+        assert_eq!(cpu.registers[0xf], 0);
+    }
+
+    #[test]
+    fn get_ith_bit_counts_from_the_top_bit() {
+        assert_eq!(get_ith_bit(0, 0b1000_0000), Some(1));
+        assert_eq!(get_ith_bit(7, 0b1000_0000), Some(0));
+        assert_eq!(get_ith_bit(6, 0b0000_0010), Some(1));
+        assert_eq!(get_ith_bit(8, 0xff), None);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::cpu::config::CpuConfig;
+use crate::cpu::font::{BigFont, Font, SmallFont, SMALL_FONT_BYTES};
+use crate::cpu::instruction::InstructionSet;
+use crate::cpu::quirks::Quirks;
+use crate::cpu::{
+    CALL_STACK_SIZE, GRAPHICS_COLUMNS, GRAPHICS_ROWS, HIRES_GRAPHICS_COLUMNS, HIRES_GRAPHICS_ROWS,
+    MEGA_MEM_SIZE, MEM_SIZE, START_ADDRESS, TWO_PAGE_GRAPHICS_ROWS, XO_MEM_SIZE,
+};
+
+// The address CHIP-8X roms are loaded at
+const CHIP8X_START_ADDRESS: u16 = 0x300;
+
+// The address ETI-660 roms are loaded at
+const ETI660_START_ADDRESS: u16 = 0x600;
+
+// The number of rows on the ETI-660 screen
+const ETI660_GRAPHICS_ROWS: usize = 48;
+
+// The number of subroutine calls the COSMAC VIP interpreters can nest
+const VIP_CALL_STACK_SIZE: usize = 12;
+
+// Where the COSMAC VIP interpreters keep the small font. The VIP draws its digits from the
+// monitor ROM at 0x8110, outside the 4K CHIP-8 can address, so the font is kept at the same
+// offset in the interpreter's 512 bytes at the bottom of memory, with the big font after it
+const VIP_FONT_ADDRESS: u16 = 0x110;
+
+// Where Octo keeps the small font, at the very start of memory with the big font after it
+const OCTO_FONT_ADDRESS: u16 = 0;
+
+// The well-known CHIP-8 interpreters, each with the instruction set, quirks and speed roms
+// written for it expect. CHIP-8III is not one of them: its arithmetic instructions are not
+// documented anywhere we could check them against, so its roms are not supported
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Platform {
+    // The original interpreter on the COSMAC VIP
+    #[default]
+    Vip,
+    // The COSMAC VIP interpreter patched for a two-page 64x64 screen
+    HiresChip8,
+    // CHIP-8E on the COSMAC VIP, with extra skips, jumps, memory and port instructions
+    Chip8E,
+    // CHIP-8I on the COSMAC VIP, with input and output port instructions
+    Chip8I,
+    // CHIP-10 on the COSMAC VIP, with a 128x64 screen
+    Chip10,
+    // CHIP-8 on the ETI-660, with a 64x48 screen and roms starting at 0x600
+    Eti660,
+    // CHIP-8X on the COSMAC VIP with the VP-590 colour board and a second keypad
+    Chip8X,
+    // CHIP-48 on the HP48 calculators
+    Chip48,
+    // SUPER-CHIP 1.1 on the HP48 calculators
+    SchipLegacy,
+    // SUPER-CHIP as modern interpreters like Octo run it
+    SchipModern,
+    // XO-CHIP as Octo runs it
+    XoChip,
+    // MegaChip, SUPER-CHIP with a 256x192 palette screen and digitized sound
+    MegaChip,
+}
+
+impl Platform {
+    // Every platform, in the order they are listed to users
+    pub const ALL: [Platform; 12] = [
+        Platform::Vip,
+        Platform::HiresChip8,
+        Platform::Chip8E,
+        Platform::Chip8I,
+        Platform::Chip10,
+        Platform::Eti660,
+        Platform::Chip8X,
+        Platform::Chip48,
+        Platform::SchipLegacy,
+        Platform::SchipModern,
+        Platform::XoChip,
+        Platform::MegaChip,
+    ];
+
+    /// Returns the name the platform is picked by on the command line, e.g. "schip-modern"
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Vip => "vip",
+            Platform::HiresChip8 => "chip-8-hires",
+            Platform::Chip8E => "chip-8e",
+            Platform::Chip8I => "chip-8i",
+            Platform::Chip10 => "chip-10",
+            Platform::Eti660 => "eti-660",
+            Platform::Chip8X => "chip-8x",
+            Platform::Chip48 => "chip-48",
+            Platform::SchipLegacy => "schip-legacy",
+            Platform::SchipModern => "schip-modern",
+            Platform::XoChip => "xo-chip",
+            Platform::MegaChip => "megachip",
+        }
+    }
+
+    /// Returns the platform with the name, or None if there is no such platform
+    ///	name - The name of the platform, as returned by name
+    pub fn from_name(name: &str) -> Option<Platform> {
+        Platform::ALL
+            .into_iter()
+            .find(|platform| platform.name() == name)
+    }
+
+    /// Returns the instructions the platform decodes
+    pub fn instruction_set(self) -> InstructionSet {
+        match self {
+            Platform::Vip | Platform::Chip10 | Platform::Eti660 | Platform::Chip48 => {
+                InstructionSet::Chip8
+            }
+            Platform::Chip8E => InstructionSet::Chip8E,
+            Platform::Chip8I => InstructionSet::Chip8I,
+            Platform::HiresChip8 => InstructionSet::HiresChip8,
+            Platform::Chip8X => InstructionSet::Chip8X,
+            Platform::SchipLegacy | Platform::SchipModern => InstructionSet::SuperChip,
+            Platform::XoChip => InstructionSet::XoChip,
+            Platform::MegaChip => InstructionSet::MegaChip,
+        }
+    }
+
+    /// Returns the interpretations the platform uses for the ambiguous instructions
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Vip
+            | Platform::HiresChip8
+            | Platform::Chip8E
+            | Platform::Chip8I
+            | Platform::Chip10
+            | Platform::Eti660
+            | Platform::Chip8X => Quirks::vip(),
+            Platform::Chip48 => Quirks::chip48(),
+            // SUPER-CHIP 1.1 still waits for the display before drawing in low resolution. It
+            // draws low resolution on the HP48's 128x64 screen and never clears it on 00FE or
+            // 00FF
+            Platform::SchipLegacy => Quirks {
+                display_wait: true,
+                half_pixel_lores_scroll: true,
+                resolution_change_keeps_screen: true,
+                ..Quirks::schip()
+            },
+            Platform::SchipModern | Platform::MegaChip => Quirks::schip(),
+            Platform::XoChip => Quirks::xo_chip(),
+        }
+    }
+
+    /// Returns the number of bytes of memory the platform has
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::XoChip => XO_MEM_SIZE,
+            Platform::MegaChip => MEGA_MEM_SIZE,
+            _ => MEM_SIZE,
+        }
+    }
+
+    /// Returns the address roms are loaded at and run from
+    pub fn start_address(self) -> u16 {
+        match self {
+            // The CHIP-8X interpreter is bigger, so roms start later
+            Platform::Chip8X => CHIP8X_START_ADDRESS,
+            // The ETI-660 interpreter and its work area fill the first 0x600 bytes
+            Platform::Eti660 => ETI660_START_ADDRESS,
+            _ => START_ADDRESS,
+        }
+    }
+
+    /// Returns the number of subroutine calls the platform can nest, None for no limit
+    pub fn stack_depth(self) -> Option<usize> {
+        match self {
+            Platform::Vip
+            | Platform::HiresChip8
+            | Platform::Chip8E
+            | Platform::Chip8I
+            | Platform::Chip10
+            | Platform::Chip8X => Some(VIP_CALL_STACK_SIZE),
+            // Octo keeps the stack outside of memory and never runs out of it
+            Platform::XoChip => None,
+            _ => Some(CALL_STACK_SIZE),
+        }
+    }
+
+    /// Returns the fonts the platform's interpreter draws digits with, and where it stores them
+    pub fn font(self) -> Font {
+        // Stores the fonts at address, the big font right after the small one
+        let at = |address: u16, small: SmallFont, big: BigFont| Font {
+            small_address: address,
+            big_address: address + SMALL_FONT_BYTES as u16,
+            ..Font::new(small, big)
+        };
+        match self {
+            Platform::Vip
+            | Platform::HiresChip8
+            | Platform::Chip8E
+            | Platform::Chip8I
+            | Platform::Chip10
+            | Platform::Chip8X => at(VIP_FONT_ADDRESS, SmallFont::Vip, BigFont::default()),
+            // Where the ETI-660 ROM keeps its digits is not documented, so they stay at 0x50
+            Platform::Eti660 => Font::new(SmallFont::Eti660, BigFont::default()),
+            // CHIP-48 and SUPER-CHIP keep their fonts in the HP48's own memory, outside the 4K
+            // CHIP-8 can address, and MegaChip does not say where it keeps them, so they stay
+            // at 0x50
+            Platform::Chip48 | Platform::SchipLegacy | Platform::MegaChip => {
+                Font::new(SmallFont::Octo, BigFont::SuperChip)
+            }
+            Platform::SchipModern => at(OCTO_FONT_ADDRESS, SmallFont::Octo, BigFont::SuperChip),
+            Platform::XoChip => at(OCTO_FONT_ADDRESS, SmallFont::Octo, BigFont::Octo),
+        }
+    }
+
+    /// Returns the memory, call stack, start address and fonts a Cpu for the platform is built
+    /// with
+    pub fn config(self) -> CpuConfig {
+        CpuConfig {
+            memory_size: self.memory_size(),
+            stack_depth: self.stack_depth(),
+            start_address: self.start_address(),
+            font: self.font(),
+        }
+    }
+
+    /// Returns the (width, height) of the screen when a rom starts
+    pub fn resolution(self) -> (usize, usize) {
+        match self {
+            Platform::HiresChip8 => (GRAPHICS_COLUMNS, TWO_PAGE_GRAPHICS_ROWS),
+            Platform::Chip10 => (HIRES_GRAPHICS_COLUMNS, HIRES_GRAPHICS_ROWS),
+            Platform::Eti660 => (GRAPHICS_COLUMNS, ETI660_GRAPHICS_ROWS),
+            _ => (GRAPHICS_COLUMNS, GRAPHICS_ROWS),
+        }
+    }
+
+    /// Returns the number of instructions to run between two timer ticks, roughly the speed of
+    /// the original interpreter
+    pub fn instructions_per_frame(self) -> u32 {
+        match self {
+            Platform::Vip
+            | Platform::HiresChip8
+            | Platform::Chip8E
+            | Platform::Chip8I
+            | Platform::Chip10
+            | Platform::Eti660
+            | Platform::Chip8X => 15,
+            Platform::Chip48 | Platform::SchipLegacy | Platform::SchipModern => 30,
+            Platform::XoChip | Platform::MegaChip => 1000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for platform in Platform::ALL {
+            assert_eq!(Platform::from_name(platform.name()), Some(platform));
+        }
+        assert_eq!(
+            Platform::from_name("schip-modern"),
+            Some(Platform::SchipModern)
+        );
+        assert_eq!(Platform::from_name("superchip"), None);
+    }
+
+    #[test]
+    fn fonts_fit_below_the_start_address() {
+        for platform in Platform::ALL {
+            assert!(platform.font().end() <= platform.start_address() as usize);
+        }
+    }
+
+    #[test]
+    fn only_xo_chip_has_64k_of_memory() {
+        for platform in Platform::ALL {
+            let xo = platform.instruction_set().has_xo_chip();
+            assert_eq!(platform.memory_size() == XO_MEM_SIZE, xo);
+        }
+        assert_eq!(Platform::MegaChip.memory_size(), MEGA_MEM_SIZE);
+    }
+}
@@ -0,0 +1,84 @@
+// Chooses between the interpretations of the instructions which CHIP-8 interpreters disagree
+// on. The default turns every quirk off, which is how this emulator ran before quirks could be
+// chosen and does not match any one interpreter. Platform::quirks gives the quirks of a real
+// interpreter
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quirks {
+    // 8xy6 and 8xyE shift register y and store the result in register x, instead of shifting
+    // register x in place
+    pub shift_uses_vy: bool,
+    // Fx55 and Fx65 leave the index register pointing past the last register stored or read
+    pub load_store_increments_i: bool,
+    // Bxnn jumps to xnn + Vx, instead of Bnnn jumping to nnn + V0
+    pub jump_uses_vx: bool,
+    // 8xy1, 8xy2 and 8xy3 set VF to 0
+    pub logic_resets_vf: bool,
+    // Sprites are cut off at the edges of the screen, instead of wrapping around to the other side
+    pub clip_sprites: bool,
+    // Dxyn halts the Cpu until the next timer tick, so at most one sprite is drawn per frame
+    pub display_wait: bool,
+    // Low resolution is drawn on the high resolution screen with every sprite pixel 2x2, so
+    // 00Cn, 00FB and 00FC scroll by half a low resolution pixel
+    pub half_pixel_lores_scroll: bool,
+    // 00FE and 00FF keep what is on the screen, instead of clearing it
+    pub resolution_change_keeps_screen: bool,
+}
+
+impl Quirks {
+    /// Returns the quirks of the original COSMAC VIP interpreter
+    pub fn vip() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_uses_vx: false,
+            logic_resets_vf: true,
+            clip_sprites: true,
+            display_wait: true,
+            half_pixel_lores_scroll: false,
+            resolution_change_keeps_screen: false,
+        }
+    }
+
+    /// Returns the quirks of the CHIP-48 interpreter on the HP48 calculators. CHIP-48 moved the
+    /// index register one short after Fx55 and Fx65, which is treated as leaving it alone
+    pub fn chip48() -> Quirks {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_uses_vx: true,
+            logic_resets_vf: false,
+            clip_sprites: true,
+            display_wait: false,
+            half_pixel_lores_scroll: false,
+            resolution_change_keeps_screen: false,
+        }
+    }
+
+    /// Returns the quirks of the XO-CHIP interpreter Octo
+    pub fn xo_chip() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_uses_vx: false,
+            logic_resets_vf: false,
+            clip_sprites: false,
+            display_wait: false,
+            half_pixel_lores_scroll: false,
+            resolution_change_keeps_screen: false,
+        }
+    }
+
+    /// Returns the quirks of the SUPER-CHIP interpreters on the HP48 calculators
+    pub fn schip() -> Quirks {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_uses_vx: true,
+            logic_resets_vf: false,
+            clip_sprites: true,
+            display_wait: false,
+            half_pixel_lores_scroll: false,
+            resolution_change_keeps_screen: false,
+        }
+    }
+}
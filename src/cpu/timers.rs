@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+// The rate the timers count down at, in ticks per second
+pub const TIMER_HZ: u32 = 60;
+
+// The host time between two timer ticks
+const TICK_PERIOD: Duration = Duration::from_nanos(1_000_000_000 / TIMER_HZ as u64);
+
+// Emulates the delay and sound timers, which both count down to zero at 60 Hz
+#[derive(Default)]
+pub struct Timers {
+    // Counts down at 60 Hz, read and written by programs to keep time
+    delay: u8,
+    // Counts down at 60 Hz, the buzzer sounds while it is above zero
+    sound: u8,
+    // Host time which has passed but not yet been turned into a tick
+    pending: Duration,
+}
+
+impl Timers {
+    /// Returns new Timers, with both timers at zero
+    pub fn new() -> Timers {
+        Timers::default()
+    }
+
+    /// Returns the value of the delay timer
+    pub fn delay(&self) -> u8 {
+        self.delay
+    }
+
+    /// Sets the value of the delay timer
+    ///	value - The value the delay timer will count down from
+    pub fn set_delay(&mut self, value: u8) {
+        self.delay = value;
+    }
+
+    /// Returns the value of the sound timer
+    pub fn sound(&self) -> u8 {
+        self.sound
+    }
+
+    /// Sets the value of the sound timer
+    ///	value - The value the sound timer will count down from
+    pub fn set_sound(&mut self, value: u8) {
+        self.sound = value;
+    }
+
+    /// Returns true while the sound timer is above zero and the buzzer should sound
+    pub fn is_sounding(&self) -> bool {
+        self.sound > 0
+    }
+
+    /// Counts both timers down once, as happens every 60th of a second
+    pub fn tick(&mut self) {
+        self.delay = self.delay.saturating_sub(1);
+        self.sound = self.sound.saturating_sub(1);
+    }
+
+    /// Counts both timers down once for every 60th of a second in elapsed, carrying any leftover
+    /// time into the next call. Returns the number of ticks which happened
+    ///	elapsed - The host time which has passed since the last call
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.pending += elapsed;
+        let mut ticks = 0;
+        while self.pending >= TICK_PERIOD {
+            self.pending -= TICK_PERIOD;
+            self.tick();
+            ticks += 1;
+        }
+        ticks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timers_start_at_zero() {
+        let timers = Timers::new();
+        assert_eq!(timers.delay(), 0);
+        assert_eq!(timers.sound(), 0);
+        assert!(!timers.is_sounding());
+    }
+
+    #[test]
+    fn tick_stops_at_zero() {
+        let mut timers = Timers::new();
+        timers.set_delay(1);
+        timers.set_sound(2);
+        timers.tick();
+        timers.tick();
+        timers.tick();
+        assert_eq!(timers.delay(), 0);
+        assert_eq!(timers.sound(), 0);
+    }
+
+    #[test]
+    fn advance_ticks_at_sixty_hz() {
+        let mut timers = Timers::new();
+        timers.set_delay(100);
+        assert_eq!(timers.advance(Duration::from_millis(10)), 0);
+        assert_eq!(timers.advance(Duration::from_millis(10)), 1);
+        assert_eq!(timers.delay(), 99);
+        assert_eq!(timers.advance(Duration::from_secs(1)), 60);
+        assert_eq!(timers.delay(), 39);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::cpu::instruction::Instruction;
+use crate::cpu::NUM_REGISTERS;
+
+// The 1802 machine cycles the COSMAC VIP runs each 60th of a second: a 1.76 MHz clock, with 8
+// clock cycles to each machine cycle
+pub const VIP_CYCLES_PER_FRAME: u32 = 3668;
+
+// The machine cycles of each frame taken by the display, which reads 8 bytes by DMA for each of
+// its 128 lines, and by the interrupt routine which counts the timers down
+const VIP_DISPLAY_CYCLES: u32 = 1024 + 46;
+
+// The machine cycles of each frame left for the interpreter to run instructions in
+pub const VIP_FRAME_BUDGET: u32 = VIP_CYCLES_PER_FRAME - VIP_DISPLAY_CYCLES;
+
+// The machine cycles every 1802 instruction takes. The long branches and skips take three, the
+// interpreter's routines are costed as if they used none
+const CYCLES_PER_OP: u32 = 2;
+
+// The 1802 instructions the interpreter runs to fetch any instruction and jump to the routine
+// which executes it
+const FETCH_OPS: u32 = 8;
+
+/// Returns an estimate of the 1802 machine cycles the COSMAC VIP interpreter takes to run the
+/// instruction. Each routine is costed by the 1802 instructions it needs for the work the
+/// instruction does, e.g. a store, a decrement and a branch for each of the 256 bytes 00E0
+/// clears, at two machine cycles each. The counts are estimates, not taken from a listing of
+/// the interpreter. Instructions the VIP does not have cost as much as a jump. Dxyn costs its
+/// drawing time only, which grows with its rows and with how far each row is shifted to reach
+/// its column. The wait for the vertical blank is up to the caller
+///	instruction - The instruction which was run
+///	skipped - Whether the instruction skipped the next one, skips take longer when they do
+///	registers - The registers before the instruction ran, which hold the column Dxyn draws at
+pub fn vip_cycles(instruction: Instruction, skipped: bool, registers: &[u8; NUM_REGISTERS]) -> u32 {
+    use Instruction::*;
+
+    // Taken skips add two to the program counter a second time
+    let skip = |ops: u32| if skipped { ops + 2 } else { ops };
+    let ops = match instruction {
+        Cls => 4 + 256 * 3,
+        Ret | CallAddr { .. } => 10,
+        JpAddr { .. } | JpV0Addr { .. } => 6,
+        SeVxByte { .. } | SneVxByte { .. } => skip(4),
+        SeVxVy { .. } | SneVxVy { .. } | SkpVx { .. } | SknpVx { .. } => skip(6),
+        LdVxByte { .. } => 3,
+        AddVxByte { .. } => 5,
+        // The interpreter writes the 1802 ALU instruction for the operation into memory and
+        // runs it on Vx and Vy, then stores the result and DF
+        LdVxVy { .. }
+        | OrVxVy { .. }
+        | AndVxVy { .. }
+        | XorVxVy { .. }
+        | AddVxVy { .. }
+        | SubVxVy { .. }
+        | ShrVxVy { .. }
+        | SubnVxVy { .. }
+        | ShlVxVy { .. } => 12,
+        LdIAddr { .. } => 4,
+        RndVxByte { .. } => 10,
+        // Each row is shifted right into place across two bytes a bit at a time, once for each
+        // column past a byte boundary, then both bytes are XORed onto the screen and checked for
+        // collisions
+        DrwVxVyNibble { x, n, .. } => {
+            let shifts = (registers[x as usize] & 7) as u32;
+            20 + n as u32 * (16 + 4 * shifts)
+        }
+        LdVxDt { .. } | LdVxK { .. } | LdDtVx { .. } | LdStVx { .. } => 3,
+        AddIVx { .. } => 6,
+        LdFVx { .. } => 6,
+        // The digits are found by repeated subtraction, taking at most 9 + 9 + 2 rounds
+        LdBVx { .. } => 8 + 20 * 3,
+        LdIVx { x } | LdVxI { x } => 4 + 4 * (x as u32 + 1),
+        _ => 6,
+    };
+    (FETCH_OPS + ops) * CYCLES_PER_OP
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The registers of a freshly started Cpu
+    const NO_REGISTERS: [u8; NUM_REGISTERS] = [0; NUM_REGISTERS];
+
+    #[test]
+    fn cls_costs_a_loop_over_the_256_display_bytes() {
+        assert_eq!(vip_cycles(Instruction::Cls, false, &NO_REGISTERS), 1560);
+    }
+
+    #[test]
+    fn every_instruction_costs_at_least_its_fetch() {
+        let ld = Instruction::LdVxByte { x: 0, nn: 1 };
+        assert_eq!(vip_cycles(ld, false, &NO_REGISTERS), 22);
+        assert_eq!(
+            vip_cycles(Instruction::JpAddr { nnn: 0x200 }, false, &NO_REGISTERS),
+            28
+        );
+        assert_eq!(
+            vip_cycles(Instruction::Unknown(0x0000), false, &NO_REGISTERS),
+            28
+        );
+    }
+
+    #[test]
+    fn skips_cost_more_when_taken() {
+        let se = Instruction::SeVxVy { x: 0, y: 1 };
+        assert_eq!(vip_cycles(se, false, &NO_REGISTERS), 28);
+        assert_eq!(vip_cycles(se, true, &NO_REGISTERS), 32);
+    }
+
+    #[test]
+    fn sprites_cost_more_with_more_rows_and_shifts() {
+        let sprite = |n| Instruction::DrwVxVyNibble { x: 0, y: 1, n };
+        assert_eq!(vip_cycles(sprite(1), false, &NO_REGISTERS), 88);
+        assert_eq!(vip_cycles(sprite(15), false, &NO_REGISTERS), 536);
+        let mut registers = NO_REGISTERS;
+        registers[0] = 8;
+        assert_eq!(vip_cycles(sprite(1), false, &registers), 88);
+        registers[0] = 7;
+        assert_eq!(vip_cycles(sprite(1), false, &registers), 144);
+        assert_eq!(vip_cycles(sprite(15), false, &registers), 1376);
+        assert!(vip_cycles(sprite(15), false, &registers) < VIP_FRAME_BUDGET);
+    }
+
+    #[test]
+    fn register_stores_cost_more_with_more_registers() {
+        assert_eq!(
+            vip_cycles(Instruction::LdIVx { x: 0 }, false, &NO_REGISTERS),
+            32
+        );
+        assert_eq!(
+            vip_cycles(Instruction::LdIVx { x: 15 }, false, &NO_REGISTERS),
+            152
+        );
+    }
+}
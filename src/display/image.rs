@@ -0,0 +1,68 @@
+use crate::display::{DisplaySink, Frame};
+use std::convert::Infallible;
+
+// An RGBA image in memory which each frame is copied into, e.g. for screenshots or for an
+// embedder's own renderer to read from
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RgbaImage {
+    // The number of pixels in each row
+    width: usize,
+    // The number of rows
+    height: usize,
+    // The RGBA colour of each pixel, row by row
+    pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Returns an empty image, which takes the resolution of the first frame shown in it
+    pub fn new() -> RgbaImage {
+        RgbaImage::default()
+    }
+
+    /// Returns the number of pixels in each row
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the RGBA colour of each pixel, row by row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Returns the RGBA colour of the pixel at (x, y)
+    ///	x - The column of the pixel, must be less than the width
+    ///	y - The row of the pixel, must be less than the height
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * self.width + x) * 4;
+        [
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ]
+    }
+}
+
+impl DisplaySink for RgbaImage {
+    type Error = Infallible;
+
+    fn show(&mut self, frame: &Frame) -> Result<(), Infallible> {
+        let (width, height) = frame.resolution();
+        if (width, height) != (self.width, self.height) {
+            *self = RgbaImage {
+                width,
+                height,
+                pixels: vec![0; width * height * 4],
+            };
+        } else if !frame.is_dirty() {
+            return Ok(());
+        }
+        frame.write_rgba(&mut self.pixels);
+        Ok(())
+    }
+}
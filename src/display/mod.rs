@@ -0,0 +1,183 @@
+mod image;
+mod palette;
+mod terminal;
+#[cfg(feature = "window")]
+mod window;
+
+pub use crate::display::image::RgbaImage;
+pub use crate::display::palette::{Palette, PaletteError, Theme, MAX_PALETTE_COLOURS};
+pub use crate::display::terminal::{TerminalSink, TextMode};
+#[cfg(feature = "window")]
+pub use crate::display::window::PixelsSink;
+
+use crate::cpu::{ColourMap, Cpu, Graphics, MegaChip, MEGA_GRAPHICS_COLUMNS, MEGA_GRAPHICS_ROWS};
+
+// Somewhere the screen of a Cpu is shown, e.g. a window, an image or a terminal
+pub trait DisplaySink {
+    // Why the screen could not be shown
+    type Error;
+
+    /// Shows the frame. Sinks which keep what they showed last may skip frames which are not
+    /// dirty
+    ///	frame - The screen to show
+    fn show(&mut self, frame: &Frame) -> Result<(), Self::Error>;
+}
+
+// A view of the screen a Cpu is showing, handed to a DisplaySink
+#[derive(Clone, Copy, Debug)]
+pub struct Frame<'a> {
+    // The CHIP-8 screen, with the planes each pixel is on in
+    graphics: &'a Graphics,
+    // The colours of the CHIP-8 screen, by the planes each pixel is on in
+    palette: &'a Palette,
+    // The CHIP-8X colours of the screen, if there are any
+    colour_map: Option<&'a ColourMap>,
+    // The MegaChip screen, if MegaChip mode is on, which is shown instead of the CHIP-8 screen
+    megachip: Option<&'a MegaChip>,
+    // Whether the screen has changed since it was last shown
+    dirty: bool,
+}
+
+impl<'a> Frame<'a> {
+    /// Returns a view of the screen the Cpu is showing
+    ///	cpu - The Cpu whose screen should be viewed
+    pub fn new(cpu: &'a Cpu) -> Frame<'a> {
+        Frame {
+            graphics: cpu.graphics(),
+            palette: cpu.palette(),
+            colour_map: cpu.colour_map(),
+            megachip: cpu.megachip().filter(|mega| mega.is_enabled()),
+            dirty: cpu.is_screen_dirty(),
+        }
+    }
+
+    /// Returns the (width, height) of the screen being shown
+    pub fn resolution(&self) -> (usize, usize) {
+        match self.megachip {
+            Some(_) => (MEGA_GRAPHICS_COLUMNS, MEGA_GRAPHICS_ROWS),
+            None => (self.graphics.width(), self.graphics.height()),
+        }
+    }
+
+    /// Returns the CHIP-8 screen, with the planes each pixel is on in. While MegaChip mode is on
+    /// this is not what is shown, the screen only exists as colours
+    pub fn graphics(&self) -> &'a Graphics {
+        self.graphics
+    }
+
+    /// Returns true if the screen may have changed since it was last shown
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Returns the RGBA colour of pixels which are off, black while MegaChip mode is on
+    pub fn background(&self) -> [u8; 4] {
+        match (self.megachip, self.colour_map) {
+            (Some(_), _) => [0, 0, 0, 0xff],
+            (None, Some(colour_map)) => colour_map.rgba(0, 0, false),
+            (None, None) => self.palette.rgba(0),
+        }
+    }
+
+    /// Writes the RGBA colour of each pixel, row by row, into out, which must hold 4 bytes for
+    /// each pixel of the resolution
+    ///	out - The RGBA pixels to write
+    pub fn write_rgba(&self, out: &mut [u8]) {
+        let out = out.chunks_exact_mut(4);
+        if let Some(mega) = self.megachip {
+            for (colour, pix) in mega.frame().zip(out) {
+                pix.copy_from_slice(&colour);
+            }
+            return;
+        }
+        let width = self.graphics.width();
+        let pixels = self.graphics.rows().flatten().enumerate();
+        for ((i, &planes), pix) in pixels.zip(out) {
+            let colour = match self.colour_map {
+                Some(colour_map) => colour_map.rgba(i % width, i / width, planes != 0),
+                None => self.palette.rgba(planes),
+            };
+            pix.copy_from_slice(&colour);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{CpuConfig, GRAPHICS_COLUMNS, GRAPHICS_ROWS};
+
+    #[test]
+    fn screen_is_clean_once_presented_until_it_is_drawn_on() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        let mut image = RgbaImage::new();
+        assert!(cpu.is_screen_dirty());
+        cpu.present(&mut image).unwrap();
+        assert!(!cpu.is_screen_dirty());
+        assert_eq!(
+            (image.width(), image.height()),
+            (GRAPHICS_COLUMNS, GRAPHICS_ROWS)
+        );
+        assert_eq!(image.pixel(0, 0), Palette::default().rgba(0));
+
+        cpu.load_rom_bytes(&[0x60, 0x01, 0xa0, 0x50, 0xd0, 0x05])
+            .unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert!(!cpu.is_screen_dirty());
+        cpu.cycle().unwrap();
+        assert!(cpu.is_screen_dirty());
+        cpu.present(&mut image).unwrap();
+        assert_eq!(image.pixel(0, 1), Palette::default().rgba(0));
+        assert_eq!(image.pixel(1, 1), Palette::default().rgba(1));
+    }
+
+    #[test]
+    fn changing_the_palette_redraws_the_screen() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        let mut image = RgbaImage::new();
+        cpu.present(&mut image).unwrap();
+        cpu.set_palette(Theme::Octo.palette());
+        assert!(cpu.is_screen_dirty());
+        cpu.present(&mut image).unwrap();
+        assert_eq!(image.pixel(0, 0), [0x99, 0x66, 0x00, 0xff]);
+        assert_eq!(Frame::new(&cpu).background(), [0x99, 0x66, 0x00, 0xff]);
+    }
+
+    #[test]
+    fn sixteen_colour_palettes_colour_the_megachip_screen() {
+        let hex: Vec<String> = (0..16).map(|i| format!("{:06x}", i * 0x100)).collect();
+        let palette = Palette::from_hex(&hex.join(" ")).unwrap();
+        let mut cpu = Cpu::with_platform(crate::cpu::Platform::MegaChip);
+        cpu.set_palette(palette);
+        // Draw a 1x1 sprite of colour 5 at (0, 0), then show it
+        #[rustfmt::skip]
+        let rom = [
+            0x00, 0x11, 0x03, 0x01, 0x04, 0x01, 0xa2, 0x0c,
+            0xd0, 0x10, 0x00, 0xe0, 0x05,
+        ];
+        cpu.load_rom_bytes(&rom).unwrap();
+        for _ in 0..6 {
+            cpu.cycle().unwrap();
+        }
+        let mut image = RgbaImage::new();
+        cpu.present(&mut image).unwrap();
+        assert_eq!(image.pixel(0, 0), [0, 0x05, 0, 0xff]);
+        assert_eq!(image.pixel(1, 0), [0, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn frame_follows_resolution_changes() {
+        let mut cpu = Cpu::with_platform(crate::cpu::Platform::SchipModern);
+        let mut image = RgbaImage::new();
+        cpu.present(&mut image).unwrap();
+        cpu.load_rom_bytes(&[0x00, 0xff]).unwrap();
+        cpu.cycle().unwrap();
+        let frame = Frame::new(&cpu);
+        assert!(frame.is_dirty());
+        assert_eq!(frame.resolution(), (128, 64));
+        assert_eq!(frame.graphics().width(), 128);
+        cpu.present(&mut image).unwrap();
+        assert_eq!(image.pixels().len(), 128 * 64 * 4);
+    }
+}
@@ -0,0 +1,325 @@
+use crate::cpu::ALL_PLANES;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// The most colours a palette holds, enough for the first 16 colours of the MegaChip palette
+pub const MAX_PALETTE_COLOURS: usize = 16;
+
+// The reasons a palette could not be read
+#[derive(Debug)]
+pub enum PaletteError {
+    // The palette file could not be read
+    Io(io::Error),
+    // A colour is not 6 hex digits, with or without a leading #
+    BadColour(String),
+    // There are not 2, 4 or 16 colours
+    WrongCount { count: usize },
+    // The palette file names a theme which does not exist
+    UnknownTheme(String),
+    // A line of the palette file is not a comment or key = value with a known key
+    BadLine { line: usize },
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PaletteError::Io(error) => write!(f, "could not read palette: {error}"),
+            PaletteError::BadColour(colour) => write!(f, "{colour} is not a hex colour"),
+            PaletteError::WrongCount { count } => {
+                write!(f, "palette has {count} colours, but must have 2, 4 or 16")
+            }
+            PaletteError::UnknownTheme(name) => write!(f, "unknown theme {name}"),
+            PaletteError::BadLine { line } => write!(f, "palette line {line} is not key = value"),
+        }
+    }
+}
+
+impl Error for PaletteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PaletteError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PaletteError {
+    fn from(error: io::Error) -> PaletteError {
+        PaletteError::Io(error)
+    }
+}
+
+// The RGBA colours pixels are drawn in, by the planes they are on in. Monochrome palettes have a
+// background and a foreground, XO-CHIP palettes add a colour for the second plane and one for
+// pixels on in both, and 16 colour palettes also colour the MegaChip screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    // The colours, starting with one for each combination of planes, bit n set for plane n + 1.
+    // Smaller palettes are repeated to fill it, monochrome ones with the foreground
+    colours: [[u8; 4]; MAX_PALETTE_COLOURS],
+    // The number of colours the palette was made from
+    count: usize,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Theme::default().palette()
+    }
+}
+
+impl Palette {
+    /// Returns the palette of the RGB colours, or an error if there are not 2, 4 or 16 of them.
+    /// Two colours are the background and foreground, so pixels on in any plane are drawn in
+    /// the foreground. The first four of 16 colours are used for the planes, and colours 1 to 15
+    /// also stand in for the MegaChip palette colours a rom has not loaded
+    ///	colours - The RGB colour of each combination of planes, starting with no planes
+    pub fn new(colours: &[[u8; 3]]) -> Result<Palette, PaletteError> {
+        let count = colours.len();
+        if ![2, 4, MAX_PALETTE_COLOURS].contains(&count) {
+            return Err(PaletteError::WrongCount { count });
+        }
+        let mut palette = [[0, 0, 0, 0xff]; MAX_PALETTE_COLOURS];
+        for (planes, rgba) in palette.iter_mut().enumerate() {
+            let [r, g, b] = match count {
+                2 if planes != 0 => colours[1],
+                _ => colours[planes % count],
+            };
+            *rgba = [r, g, b, 0xff];
+        }
+        Ok(Palette {
+            colours: palette,
+            count,
+        })
+    }
+
+    /// Parses a palette from hex colours such as "#000000,#ffffff", separated by commas or
+    /// whitespace, as new does
+    ///	text - The colours, each 6 hex digits with or without a leading #
+    pub fn from_hex(text: &str) -> Result<Palette, PaletteError> {
+        let colours = text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|colour| !colour.is_empty())
+            .map(parse_colour)
+            .collect::<Result<Vec<_>, _>>()?;
+        Palette::new(&colours)
+    }
+
+    /// Parses a palette file. Each line is blank, a comment starting with #, or one of
+    /// `theme = NAME` to start from a theme and `colours = LIST` to set the colours as from_hex
+    /// does, later lines winning
+    ///	text - The contents of the palette file
+    pub fn from_config(text: &str) -> Result<Palette, PaletteError> {
+        let mut palette = Palette::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad_line = PaletteError::BadLine { line: i + 1 };
+            let (key, value) = line.split_once('=').ok_or(bad_line)?;
+            let value = value.trim();
+            palette = match key.trim() {
+                "theme" => Theme::from_name(value)
+                    .ok_or_else(|| PaletteError::UnknownTheme(value.to_string()))?
+                    .palette(),
+                "colours" | "colors" => Palette::from_hex(value)?,
+                _ => return Err(PaletteError::BadLine { line: i + 1 }),
+            };
+        }
+        Ok(palette)
+    }
+
+    /// Reads the palette file stored at path, as from_config does
+    ///	path - The path which we should load the palette from
+    pub fn load_file(path: &Path) -> Result<Palette, PaletteError> {
+        Palette::from_config(&fs::read_to_string(path)?)
+    }
+
+    /// Returns the RGBA colour of pixels on in the planes
+    ///	planes - The planes the pixel is on in, bit n set for plane n + 1
+    pub fn rgba(&self, planes: u8) -> [u8; 4] {
+        self.colours[(planes & ALL_PLANES) as usize]
+    }
+
+    /// Returns the 16 RGBA colours the MegaChip screen starts with, or None unless the palette
+    /// was made from 16 colours
+    pub fn megachip_colours(&self) -> Option<&[[u8; 4]; MAX_PALETTE_COLOURS]> {
+        (self.count == MAX_PALETTE_COLOURS).then_some(&self.colours)
+    }
+}
+
+/// Returns the RGB colour written as 6 hex digits, with or without a leading #
+///	text - The colour, e.g. "#ffb000"
+fn parse_colour(text: &str) -> Result<[u8; 3], PaletteError> {
+    let bad_colour = || PaletteError::BadColour(text.to_string());
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(bad_colour());
+    }
+    let value = u32::from_str_radix(hex, 16).map_err(|_| bad_colour())?;
+    let [_, r, g, b] = value.to_be_bytes();
+    Ok([r, g, b])
+}
+
+// The built in palettes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    // White on black, with greys for the XO-CHIP planes
+    #[default]
+    Mono,
+    // Green phosphor on black, like a COSMAC VIP on a green monitor
+    Vip,
+    // Dark grey on the green grey of an LCD, like the HP 48
+    Lcd,
+    // Amber phosphor on black
+    Amber,
+    // The yellows and browns Octo starts with
+    Octo,
+}
+
+impl Theme {
+    // Every theme, in the order they are listed to users
+    pub const ALL: [Theme; 5] = [
+        Theme::Mono,
+        Theme::Vip,
+        Theme::Lcd,
+        Theme::Amber,
+        Theme::Octo,
+    ];
+
+    /// Returns the name the theme is picked by on the command line, e.g. "amber"
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Mono => "mono",
+            Theme::Vip => "vip",
+            Theme::Lcd => "lcd",
+            Theme::Amber => "amber",
+            Theme::Octo => "octo",
+        }
+    }
+
+    /// Returns the theme with the name, or None if there is no such theme
+    ///	name - The name of the theme, as returned by name
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Theme::ALL.into_iter().find(|theme| theme.name() == name)
+    }
+
+    /// Returns the colours of the theme: the background, the first plane, the second plane and
+    /// both planes
+    pub fn palette(self) -> Palette {
+        let colours = match self {
+            Theme::Mono => [
+                [0x00, 0x00, 0x00],
+                [0xff, 0xff, 0xff],
+                [0xaa, 0xaa, 0xaa],
+                [0x55, 0x55, 0x55],
+            ],
+            Theme::Vip => [
+                [0x00, 0x00, 0x00],
+                [0x33, 0xff, 0x33],
+                [0x1a, 0x80, 0x1a],
+                [0x99, 0xff, 0x99],
+            ],
+            Theme::Lcd => [
+                [0xc7, 0xf0, 0xd8],
+                [0x43, 0x52, 0x3d],
+                [0x8a, 0x9e, 0x84],
+                [0x1f, 0x26, 0x1c],
+            ],
+            Theme::Amber => [
+                [0x00, 0x00, 0x00],
+                [0xff, 0xb0, 0x00],
+                [0x80, 0x58, 0x00],
+                [0xff, 0xd4, 0x80],
+            ],
+            Theme::Octo => [
+                [0x99, 0x66, 0x00],
+                [0xff, 0xcc, 0x00],
+                [0xff, 0x66, 0x00],
+                [0x66, 0x22, 0x00],
+            ],
+        };
+        Palette::new(&colours).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for theme in Theme::ALL {
+            assert_eq!(Theme::from_name(theme.name()), Some(theme));
+        }
+        assert_eq!(Theme::from_name("sepia"), None);
+    }
+
+    #[test]
+    fn two_colours_draw_every_plane_in_the_foreground() {
+        let palette = Palette::from_hex("#102030, 405060").unwrap();
+        assert_eq!(palette.rgba(0), [0x10, 0x20, 0x30, 0xff]);
+        for planes in 1..=ALL_PLANES {
+            assert_eq!(palette.rgba(planes), [0x40, 0x50, 0x60, 0xff]);
+        }
+    }
+
+    #[test]
+    fn four_colours_cover_the_xo_chip_planes() {
+        let palette = Palette::from_hex("000000 111111 222222 333333").unwrap();
+        assert_eq!(palette.rgba(0b10), [0x22, 0x22, 0x22, 0xff]);
+        assert_eq!(palette.rgba(0b11), [0x33, 0x33, 0x33, 0xff]);
+        assert_eq!(palette.rgba(0b111), [0x33, 0x33, 0x33, 0xff]);
+    }
+
+    #[test]
+    fn sixteen_colours_cover_the_planes_and_the_megachip_palette() {
+        let hex: Vec<String> = (0..16).map(|i| format!("#{:06x}", i * 0x111111)).collect();
+        let palette = Palette::from_hex(&hex.join(",")).unwrap();
+        assert_eq!(palette.rgba(0b11), [0x33, 0x33, 0x33, 0xff]);
+        let colours = palette.megachip_colours().unwrap();
+        assert_eq!(colours[15], [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(Theme::Octo.palette().megachip_colours(), None);
+    }
+
+    #[test]
+    fn bad_colours_and_counts_are_errors() {
+        assert!(matches!(
+            Palette::from_hex("#000000 #fffff"),
+            Err(PaletteError::BadColour(colour)) if colour == "#fffff"
+        ));
+        assert!(matches!(
+            Palette::from_hex("#000000 #ffffff #aaaaaa"),
+            Err(PaletteError::WrongCount { count: 3 })
+        ));
+        assert!(matches!(
+            Palette::from_hex(&["#000000"; 8].join(",")),
+            Err(PaletteError::WrongCount { count: 8 })
+        ));
+    }
+
+    #[test]
+    fn config_starts_from_a_theme_and_can_set_colours() {
+        let config = "# amber, then something else\ntheme = amber\n\n";
+        assert_eq!(
+            Palette::from_config(config).unwrap(),
+            Theme::Amber.palette()
+        );
+
+        let config = "theme = amber\ncolours = #000000,#00ff00\n";
+        let palette = Palette::from_config(config).unwrap();
+        assert_eq!(palette.rgba(1), [0, 0xff, 0, 0xff]);
+
+        assert!(matches!(
+            Palette::from_config("theme = amber\nbrightness = 11"),
+            Err(PaletteError::BadLine { line: 2 })
+        ));
+        assert!(matches!(
+            Palette::from_config("theme = sepia"),
+            Err(PaletteError::UnknownTheme(_))
+        ));
+    }
+}
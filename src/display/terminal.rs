@@ -0,0 +1,237 @@
+use crate::display::{DisplaySink, Frame};
+use std::io::{self, Write};
+
+// The character drawn for each pair of rows in half block mode, its foreground colour is the top
+// pixel and its background colour the bottom one
+const UPPER_HALF_BLOCK: char = '▀';
+
+// The blank braille character, the dots of each cell are added to it
+const BRAILLE_BLANK: u32 = 0x2800;
+
+// The bit of each dot of a braille character, indexed by [row][column] of its 2x4 cell
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+// How pixels are turned into characters
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextMode {
+    // Each character covers one column and two rows, in full colour
+    #[default]
+    HalfBlock,
+    // Each character covers two columns and four rows as braille dots, so the screen takes a
+    // quarter of the characters, but each character has only one foreground colour
+    Braille,
+}
+
+impl TextMode {
+    /// Returns the number of (columns, rows) of pixels each character covers
+    fn cell_size(self) -> (usize, usize) {
+        match self {
+            TextMode::HalfBlock => (1, 2),
+            TextMode::Braille => (2, 4),
+        }
+    }
+}
+
+// A character on the terminal and its colours
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Cell {
+    character: char,
+    foreground: [u8; 3],
+    background: [u8; 3],
+}
+
+// Shows the screen as text in a terminal which understands ANSI escape codes and 24-bit colour.
+// Only the characters which changed since the last frame are written, so redraws stay small
+// enough to play over a slow connection
+pub struct TerminalSink<W: Write> {
+    // Where the escape codes and characters are written
+    out: W,
+    // How pixels are turned into characters
+    mode: TextMode,
+    // The RGBA colour of each pixel of the frame being drawn, kept to save allocating it every
+    // frame
+    rgba: Vec<u8>,
+    // The number of characters in each row on the terminal
+    columns: usize,
+    // The characters on the terminal, row by row, empty until the first frame is drawn
+    cells: Vec<Cell>,
+}
+
+impl<W: Write> TerminalSink<W> {
+    /// Returns a TerminalSink writing to out
+    ///	out - The terminal to write to, e.g. stdout
+    ///	mode - How pixels should be turned into characters
+    pub fn new(out: W, mode: TextMode) -> TerminalSink<W> {
+        TerminalSink {
+            out,
+            mode,
+            rgba: Vec::new(),
+            columns: 0,
+            cells: Vec::new(),
+        }
+    }
+
+    /// Forgets what is on the terminal, so the next frame is drawn in full, e.g. after the
+    /// terminal has been resized or cleared
+    pub fn invalidate(&mut self) {
+        self.cells.clear();
+    }
+
+    /// Returns the terminal being written to
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    /// Returns the terminal which was written to
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Returns the characters showing the RGBA pixels, row by row
+    ///	width - The number of pixels in each row
+    ///	height - The number of rows
+    ///	background - The colour of pixels which are off, braille dots are drawn for the others
+    fn cells(&self, width: usize, height: usize, background: [u8; 4]) -> Vec<Cell> {
+        let (cell_width, cell_height) = self.mode.cell_size();
+        let pixel = |x: usize, y: usize| {
+            let i = (y.min(height - 1) * width + x.min(width - 1)) * 4;
+            [self.rgba[i], self.rgba[i + 1], self.rgba[i + 2]]
+        };
+        let rgb = [background[0], background[1], background[2]];
+        let mut cells = Vec::new();
+        for y in (0..height).step_by(cell_height) {
+            for x in (0..width).step_by(cell_width) {
+                cells.push(match self.mode {
+                    // An odd last row has nothing below it, so its bottom half matches its top
+                    TextMode::HalfBlock => Cell {
+                        character: UPPER_HALF_BLOCK,
+                        foreground: pixel(x, y),
+                        background: pixel(x, y + 1),
+                    },
+                    TextMode::Braille => {
+                        let mut dots = BRAILLE_BLANK;
+                        let mut foreground = rgb;
+                        for (row, bits) in BRAILLE_DOTS.iter().enumerate() {
+                            for (column, bit) in bits.iter().enumerate() {
+                                let (dot_x, dot_y) = (x + column, y + row);
+                                if dot_x < width && dot_y < height && pixel(dot_x, dot_y) != rgb {
+                                    foreground = pixel(dot_x, dot_y);
+                                    dots |= bit;
+                                }
+                            }
+                        }
+                        Cell {
+                            character: char::from_u32(dots).unwrap_or(' '),
+                            foreground,
+                            background: rgb,
+                        }
+                    }
+                });
+            }
+        }
+        cells
+    }
+}
+
+impl<W: Write> DisplaySink for TerminalSink<W> {
+    type Error = io::Error;
+
+    /// Writes the characters which have changed since the last frame. The whole screen is drawn
+    /// for the first frame and whenever the resolution changes
+    fn show(&mut self, frame: &Frame) -> io::Result<()> {
+        if !frame.is_dirty() && !self.cells.is_empty() {
+            return Ok(());
+        }
+        let (width, height) = frame.resolution();
+        self.rgba.resize(width * height * 4, 0);
+        frame.write_rgba(&mut self.rgba);
+        let cells = self.cells(width, height, frame.background());
+        let columns = width.div_ceil(self.mode.cell_size().0);
+        if columns != self.columns || cells.len() != self.cells.len() {
+            write!(self.out, "\x1b[0m\x1b[2J")?;
+            self.cells.clear();
+            self.columns = columns;
+        }
+
+        // Where the cursor is and the colours it writes in, so they are only sent when they change
+        let mut cursor = None;
+        let mut pen = None;
+        for (i, cell) in cells.iter().enumerate() {
+            if self.cells.get(i) == Some(cell) {
+                continue;
+            }
+            let position = (i / columns, i % columns);
+            if cursor != Some(position) {
+                write!(self.out, "\x1b[{};{}H", position.0 + 1, position.1 + 1)?;
+            }
+            let colours = (cell.foreground, cell.background);
+            if pen != Some(colours) {
+                let ([fr, fg, fb], [br, bg, bb]) = colours;
+                write!(self.out, "\x1b[38;2;{fr};{fg};{fb};48;2;{br};{bg};{bb}m")?;
+                pen = Some(colours);
+            }
+            write!(self.out, "{}", cell.character)?;
+            cursor = Some((position.0, position.1 + 1));
+        }
+        if pen.is_some() {
+            write!(self.out, "\x1b[0m")?;
+        }
+        self.cells = cells;
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{Cpu, CpuConfig, GRAPHICS_COLUMNS, GRAPHICS_ROWS};
+
+    // Draws the font sprite for 0 in the top left corner
+    fn draw_zero(cpu: &mut Cpu) {
+        cpu.load_rom_bytes(&[0xa0, 0x50, 0xd0, 0x05]).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+    }
+
+    #[test]
+    fn first_frame_is_drawn_in_full_then_only_changes() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        let mut sink = TerminalSink::new(Vec::new(), TextMode::HalfBlock);
+        cpu.present(&mut sink).unwrap();
+        let text = String::from_utf8(sink.get_ref().clone()).unwrap();
+        assert!(text.starts_with("\x1b[0m\x1b[2J\x1b[1;1H\x1b[38;2;0;0;0;48;2;0;0;0m▀▀"));
+        assert_eq!(
+            text.matches(UPPER_HALF_BLOCK).count(),
+            GRAPHICS_COLUMNS * GRAPHICS_ROWS / 2
+        );
+
+        cpu.present(&mut sink).unwrap();
+        assert_eq!(sink.get_ref().len(), text.len());
+
+        draw_zero(&mut cpu);
+        cpu.present(&mut sink).unwrap();
+        let redraw = String::from_utf8(sink.into_inner()[text.len()..].to_vec()).unwrap();
+        assert!(redraw.starts_with("\x1b[1;1H"));
+        // The 0 covers 4x3 characters, but the two in the middle of its second row stay blank
+        assert_eq!(redraw.matches(UPPER_HALF_BLOCK).count(), 4 * 3 - 2);
+        assert_eq!(redraw.matches("\x1b[2;").count(), 2);
+    }
+
+    #[test]
+    fn braille_packs_eight_pixels_into_each_character() {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        draw_zero(&mut cpu);
+        let mut sink = TerminalSink::new(Vec::new(), TextMode::Braille);
+        cpu.present(&mut sink).unwrap();
+        let text = String::from_utf8(sink.into_inner()).unwrap();
+        let characters: Vec<char> = text
+            .chars()
+            .filter(|&c| c as u32 >= BRAILLE_BLANK)
+            .collect();
+        assert_eq!(characters.len(), GRAPHICS_COLUMNS / 2 * GRAPHICS_ROWS / 4);
+        // The top of the 0 is ####, then #..# three times
+        assert_eq!(characters[0], '⡏');
+        assert_eq!(characters[1], '⢹');
+        assert_eq!(characters[2], '⠀');
+    }
+}
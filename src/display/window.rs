@@ -0,0 +1,51 @@
+use crate::display::{DisplaySink, Frame};
+use pixels::{Pixels, TextureError};
+
+// Shows the screen in a window through the pixels crate, resizing its buffer whenever the
+// resolution changes
+pub struct PixelsSink {
+    // The buffer and surface the screen is drawn to
+    pixels: Pixels,
+    // The (width, height) of the buffer
+    size: (usize, usize),
+}
+
+impl PixelsSink {
+    /// Returns a PixelsSink drawing through pixels
+    ///	pixels - The buffer and surface of the window
+    ///	width - The number of pixels in each row of the buffer
+    ///	height - The number of rows of the buffer
+    pub fn new(pixels: Pixels, width: usize, height: usize) -> PixelsSink {
+        PixelsSink {
+            pixels,
+            size: (width, height),
+        }
+    }
+
+    /// Resizes the surface the buffer is scaled onto, call it when the window is resized
+    ///	width - The new width of the window, in physical pixels
+    ///	height - The new height of the window, in physical pixels
+    pub fn resize_surface(&mut self, width: u32, height: u32) -> Result<(), TextureError> {
+        self.pixels.resize_surface(width, height)
+    }
+}
+
+impl DisplaySink for PixelsSink {
+    type Error = pixels::Error;
+
+    /// Draws the frame into the buffer if it has changed, then renders the buffer to the window.
+    /// The window is rendered even when nothing has changed, since it may have been uncovered
+    fn show(&mut self, frame: &Frame) -> Result<(), pixels::Error> {
+        let resolution = frame.resolution();
+        let resized = resolution != self.size;
+        if resized {
+            self.pixels
+                .resize_buffer(resolution.0 as u32, resolution.1 as u32)?;
+            self.size = resolution;
+        }
+        if resized || frame.is_dirty() {
+            frame.write_rgba(self.pixels.frame_mut());
+        }
+        self.pixels.render()
+    }
+}
@@ -0,0 +1,126 @@
+use crate::cpu::Cpu;
+use std::fmt::Write;
+
+// The FNV-1a offset basis and prime, used to hash the screen and sound
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Returns the screen as ASCII art, one line per row, '#' for pixels which are on and '.' for
+/// pixels which are off
+///	cpu - The Cpu whose screen should be dumped
+pub fn to_ascii(cpu: &Cpu) -> String {
+    let graphics = cpu.graphics();
+    let mut art = String::with_capacity((graphics.width() + 1) * graphics.height());
+    for row in graphics.rows() {
+        art.extend(
+            row.iter()
+                .map(|&planes| if planes != 0 { '#' } else { '.' }),
+        );
+        art.push('\n');
+    }
+    art
+}
+
+/// Returns the screen as a plain (P1) PBM image, where 1 is a pixel which is on
+///	cpu - The Cpu whose screen should be dumped
+pub fn to_pbm(cpu: &Cpu) -> String {
+    let graphics = cpu.graphics();
+    let mut image = format!("P1\n{} {}\n", graphics.width(), graphics.height());
+    for row in graphics.rows() {
+        let bits: Vec<&str> = row
+            .iter()
+            .map(|&planes| if planes != 0 { "1" } else { "0" })
+            .collect();
+        writeln!(image, "{}", bits.join(" ")).unwrap();
+    }
+    image
+}
+
+/// Returns a 64-bit FNV-1a hash of the screen's resolution followed by its pixels. It only
+/// depends on the resolution and which planes each pixel is on in, so it is the same across
+/// runs, builds and platforms and can be compared against golden values
+///	cpu - The Cpu whose screen should be hashed
+pub fn screen_hash(cpu: &Cpu) -> u64 {
+    let (width, height) = cpu.resolution();
+    let resolution = [width as u64, height as u64];
+    let pixels = cpu.graphics().rows().flatten().map(|&planes| planes as u64);
+    resolution
+        .into_iter()
+        .chain(pixels)
+        .fold(FNV_OFFSET_BASIS, |hash, value| {
+            (hash ^ value).wrapping_mul(FNV_PRIME)
+        })
+}
+
+/// Returns a 64-bit FNV-1a hash of 16-bit samples, e.g. the sound a rom made, to compare against
+/// golden values
+///	samples - The samples which should be hashed
+pub fn audio_hash(samples: &[i16]) -> u64 {
+    samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{CpuConfig, GRAPHICS_ROWS};
+    use crate::Platform;
+
+    // Draws the font sprite for 0 in the top left corner
+    fn cpu_showing_zero() -> Cpu {
+        let mut cpu = Cpu::new(CpuConfig::default());
+        cpu.load_rom_bytes(&[0xa0, 0x50, 0xd0, 0x05]).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        cpu
+    }
+
+    #[test]
+    fn ascii_marks_pixels_which_are_on() {
+        let art = to_ascii(&cpu_showing_zero());
+        let lines: Vec<&str> = art.lines().collect();
+        assert_eq!(lines.len(), GRAPHICS_ROWS);
+        assert!(lines[0].starts_with("####."));
+        assert!(lines[1].starts_with("#..#."));
+        assert!(lines[5].chars().all(|c| c == '.'));
+    }
+
+    #[test]
+    fn pbm_has_header_and_one_line_per_row() {
+        let image = to_pbm(&cpu_showing_zero());
+        let mut lines = image.lines();
+        assert_eq!(lines.next(), Some("P1"));
+        assert_eq!(lines.next(), Some("64 32"));
+        assert!(lines.next().unwrap().starts_with("1 1 1 1 0"));
+        assert_eq!(lines.count(), GRAPHICS_ROWS - 1);
+    }
+
+    #[test]
+    fn hash_changes_with_screen() {
+        let blank = screen_hash(&Cpu::new(CpuConfig::default()));
+        assert_eq!(blank, screen_hash(&Cpu::new(CpuConfig::default())));
+        assert_ne!(blank, screen_hash(&cpu_showing_zero()));
+    }
+
+    #[test]
+    fn hash_changes_with_resolution() {
+        let mut cpu = Cpu::with_platform(Platform::SchipModern);
+        let lores = screen_hash(&cpu);
+        cpu.load_rom_bytes(&[0x00, 0xff]).unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.resolution(), (128, 64));
+        assert!(cpu.graphics().rows().flatten().all(|&planes| planes == 0));
+        assert_ne!(lores, screen_hash(&cpu));
+    }
+
+    #[test]
+    fn audio_hash_changes_with_samples() {
+        assert_eq!(audio_hash(&[]), FNV_OFFSET_BASIS);
+        assert_eq!(audio_hash(&[1, -1]), audio_hash(&[1, -1]));
+        assert_ne!(audio_hash(&[1, -1]), audio_hash(&[-1, 1]));
+    }
+}
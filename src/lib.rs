@@ -0,0 +1,18 @@
+//! A CHIP-8 emulator core, with no windowing dependencies.
+//!
+//! `Cpu` loads and runs roms. The host drives it by calling `Cpu::cycle` for each instruction,
+//! counting the timers down at 60 Hz and pressing keys on its keypad, then reads the screen
+//! back out of `Cpu::graphics`, or hands it to a `display::DisplaySink` with `Cpu::present`.
+//! `audio::Audio` turns the sound timer into samples for a backend. `cli` holds the command line
+//! options the frontends share.
+
+pub mod audio;
+pub mod cli;
+pub mod cpu;
+pub mod display;
+pub mod dump;
+
+pub use cpu::{
+    decode, decode_for, Cpu, CpuConfig, CpuError, FaultPolicy, Graphics, Instruction,
+    InstructionSet, Keypad, Platform, Quirks, RomError, Timers,
+};
@@ -1,72 +1,137 @@
-mod cpu;
-
-use cpu::Cpu;
-use pixels::{Error, Pixels, SurfaceTexture};
+#[cfg(feature = "audio")]
+use chip8_emulator::audio::{Audio, LiveBackend};
+use chip8_emulator::cli::{self, CpuOptions, PaletteOptions};
+use chip8_emulator::cpu::TIMER_HZ;
+use chip8_emulator::display::PixelsSink;
+use pixels::{Pixels, SurfaceTexture};
 use std::env;
-use std::path::Path;
+use std::error::Error;
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 use winit::dpi::LogicalSize;
+use winit::event::{Event, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
+use winit::keyboard::KeyCode;
 use winit::window::WindowBuilder;
 use winit_input_helper::WinitInputHelper;
 
-const HEIGHT: u32 = 32;
-
-const WIDTH: u32 = 64;
-
-fn main() -> Result<(), Error> {
-    // let event_loop = eventloop::new();
-    // let window = window::new(&event_loop).unwrap();
-    // let size = window.inner_size();
-
-    // let surface_texture = surfacetexture::new(size.width, size.height, &window);
+// How many times bigger than the CHIP-8 screen the window starts
+const SCALE: f64 = 10.0;
+
+// The host time each frame lasts, the timers tick once per frame
+const FRAME_PERIOD: Duration = Duration::from_nanos(1_000_000_000 / TIMER_HZ as u64);
+
+// Maps the left side of a QWERTY keyboard onto the hex keypad:
+//	1 2 3 4		1 2 3 C
+//	Q W E R		4 5 6 D
+//	A S D F		7 8 9 E
+//	Z X C V		A 0 B F
+const KEY_MAP: [(KeyCode, u8); 16] = [
+    (KeyCode::Digit1, 0x1),
+    (KeyCode::Digit2, 0x2),
+    (KeyCode::Digit3, 0x3),
+    (KeyCode::Digit4, 0xc),
+    (KeyCode::KeyQ, 0x4),
+    (KeyCode::KeyW, 0x5),
+    (KeyCode::KeyE, 0x6),
+    (KeyCode::KeyR, 0xd),
+    (KeyCode::KeyA, 0x7),
+    (KeyCode::KeyS, 0x8),
+    (KeyCode::KeyD, 0x9),
+    (KeyCode::KeyF, 0xe),
+    (KeyCode::KeyZ, 0xa),
+    (KeyCode::KeyX, 0x0),
+    (KeyCode::KeyC, 0xb),
+    (KeyCode::KeyV, 0xf),
+];
+
+// The rom to run, the platform, fonts and timing to run it with and the colours to show it in
+struct Game {
+    path: PathBuf,
+    cpu: CpuOptions,
+    palette: PaletteOptions,
+}
 
-    // let pixels = pixels::new(graphics_columns, graphics_rows, surface_texture)
-    //     .expect("something went wrong when initializing");
-    // let mut cpu = cpu::new(pixels);
-    // match get_game() {
-    //     some(ref path) => cpu.load_rom(path),
-    //     _ => return,
-    // };
-    // let running = true;
-    // while running {
-    //     cpu.cycle();
-    // }
+fn main() -> ExitCode {
+    let game = match get_game(env::args().skip(1)) {
+        Ok(game) => game,
+        Err(message) => {
+            eprintln!("{message}");
+            eprintln!(
+                "usage: chip8_emulator {} {} <rom>",
+                cli::CPU_USAGE,
+                cli::PALETTE_USAGE
+            );
+            cli::print_cpu_names();
+            cli::print_palette_names();
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(game) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
 
-env_logger::init();
-    let event_loop = EventLoop::new();
+/// Runs the game in a window until it is closed, returning the error which stopped it early
+///	game - The rom and options to run it with
+fn run(game: Game) -> Result<(), Box<dyn Error>> {
+    let mut cpu = game.cpu.build(&game.path)?;
+    cpu.set_palette(game.palette.palette()?);
+    #[cfg(feature = "audio")]
+    let mut audio = LiveBackend::new()
+        .inspect_err(|error| eprintln!("{error}, running without sound"))
+        .ok()
+        .map(Audio::new);
+    let instructions_per_frame = game.cpu.instructions_per_frame();
+
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Poll);
     let mut input = WinitInputHelper::new();
 
+    // The platform picks the resolution the screen starts at
+    let (width, height) = {
+        let (width, height) = cpu.resolution();
+        (width as u32, height as u32)
+    };
+
     let window = {
-        let size = LogicalSize::new(WIDTH as f64, HEIGHT as f64);
-        let scaled_size = LogicalSize::new(WIDTH as f64 * 3.0, HEIGHT as f64 * 3.0);
+        let size = LogicalSize::new(width as f64, height as f64);
+        let scaled_size = LogicalSize::new(width as f64 * SCALE, height as f64 * SCALE);
+        let rom_name = game.path.file_name().unwrap_or_default().to_string_lossy();
         WindowBuilder::new()
-            .with_title("Conway's Game of Life")
+            .with_title(format!("CHIP-8 - {rom_name}"))
             .with_inner_size(scaled_size)
             .with_min_inner_size(size)
-            .build(&event_loop)
-            .unwrap()
+            .build(&event_loop)?
     };
 
-    let mut pixels = {
+    let mut sink = {
         let window_size = window.inner_size();
         let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(WIDTH, HEIGHT, surface_texture)?
+        let pixels = Pixels::new(width, height, surface_texture)?;
+        PixelsSink::new(pixels, width as usize, height as usize)
     };
 
-    let mut cpu = cpu::new(pixels);
-
     let mut paused = false;
+    let mut last_frame = Instant::now();
+    let mut behind = Duration::ZERO;
 
-    let mut draw_state: Option<bool> = None;
-
-    event_loop.run(move |event, _, control_flow| {
+    event_loop.run(move |event, elwt| {
         // The one and only event that winit_input_helper doesn't have for us...
-        if let Event::RedrawRequested(_) = event {
-            life.draw(pixels.frame_mut());
-            if let Err(err) = pixels.render() {
-                log_error("pixels.render", err);
-                *control_flow = ControlFlow::Exit;
+        if let Event::WindowEvent {
+            event: WindowEvent::RedrawRequested,
+            ..
+        } = event
+        {
+            // The sink follows SUPER-CHIP and MegaChip roms changing the resolution
+            if let Err(err) = cpu.present(&mut sink) {
+                eprintln!("pixels.render: {err}");
+                elwt.exit();
                 return;
             }
         }
@@ -75,96 +140,76 @@ env_logger::init();
         // It returns `true` when it is time to update our game state and request a redraw.
         if input.update(&event) {
             // Close events
-            if input.key_pressed(VirtualKeyCode::Escape) || input.close_requested() {
-                *control_flow = ControlFlow::Exit;
+            if input.key_pressed(KeyCode::Escape) || input.close_requested() {
+                elwt.exit();
                 return;
             }
-            if input.key_pressed(VirtualKeyCode::P) {
+            if input.key_pressed(KeyCode::KeyP) {
                 paused = !paused;
             }
-            if input.key_pressed_os(VirtualKeyCode::Space) {
-                // Space is frame-step, so ensure we're paused
-                paused = true;
-            }
-            if input.key_pressed(VirtualKeyCode::R) {
-                life.randomize();
-            }
-            // Handle mouse. This is a bit involved since support some simple
-            // line drawing (mostly because it makes nice looking patterns).
-            let (mouse_cell, mouse_prev_cell) = input
-                .mouse()
-                .map(|(mx, my)| {
-                    let (dx, dy) = input.mouse_diff();
-                    let prev_x = mx - dx;
-                    let prev_y = my - dy;
-
-                    let (mx_i, my_i) = pixels
-                        .window_pos_to_pixel((mx, my))
-                        .unwrap_or_else(|pos| pixels.clamp_pixel_pos(pos));
-
-                    let (px_i, py_i) = pixels
-                        .window_pos_to_pixel((prev_x, prev_y))
-                        .unwrap_or_else(|pos| pixels.clamp_pixel_pos(pos));
-
-                    (
-                        (mx_i as isize, my_i as isize),
-                        (px_i as isize, py_i as isize),
-                    )
-                })
-                .unwrap_or_default();
-
-            if input.mouse_pressed(0) {
-                debug!("Mouse click at {mouse_cell:?}");
-                draw_state = Some(life.toggle(mouse_cell.0, mouse_cell.1));
-            } else if let Some(draw_alive) = draw_state {
-                let release = input.mouse_released(0);
-                let held = input.mouse_held(0);
-                debug!("Draw at {mouse_prev_cell:?} => {mouse_cell:?}");
-                debug!("Mouse held {held:?}, release {release:?}");
-                // If they either released (finishing the drawing) or are still
-                // in the middle of drawing, keep going.
-                if release || held {
-                    debug!("Draw line of {draw_alive:?}");
-                    life.set_line(
-                        mouse_prev_cell.0,
-                        mouse_prev_cell.1,
-                        mouse_cell.0,
-                        mouse_cell.1,
-                        draw_alive,
-                    );
+            for (code, key) in KEY_MAP {
+                if input.key_pressed(code) {
+                    cpu.press_key(key);
                 }
-                // If they let go or are otherwise not clicking anymore, stop drawing.
-                if release || !held {
-                    debug!("Draw end");
-                    draw_state = None;
+                if input.key_released(code) {
+                    cpu.release_key(key);
                 }
             }
             // Resize the window
             if let Some(size) = input.window_resized() {
-                if let Err(err) = pixels.resize_surface(size.width, size.height) {
-                    log_error("pixels.resize_surface", err);
-                    *control_flow = ControlFlow::Exit;
+                if let Err(err) = sink.resize_surface(size.width, size.height) {
+                    eprintln!("pixels.resize_surface: {err}");
+                    elwt.exit();
                     return;
                 }
             }
-            if !paused || input.key_pressed_os(VirtualKeyCode::Space) {
-                life.update();
+
+            // Run as many frames as the host time since the last update covers
+            let now = Instant::now();
+            if !paused {
+                // Catch up if the host fell far behind, e.g. while the window was being dragged,
+                // rather than running a burst of frames
+                behind = (behind + (now - last_frame)).min(2 * FRAME_PERIOD);
+            }
+            last_frame = now;
+            while behind >= FRAME_PERIOD {
+                behind -= FRAME_PERIOD;
+                if let Err(err) = cpu.run_frame(instructions_per_frame) {
+                    eprintln!("{err}");
+                    elwt.exit();
+                    return;
+                }
+                #[cfg(feature = "audio")]
+                if let Some(audio) = &mut audio {
+                    audio.frame(&cpu);
+                }
+                window.request_redraw();
             }
-            window.request_redraw();
         }
+    })?;
+    Ok(())
 }
 
-fn get_game() -> Option<PathBuf> {
-    if env::args().len() != 2 {
-        println!("wrong");
-        return None;
+/// Parses the command line arguments, after the program name, into the rom to run and the
+/// options to run it with
+///	args - The arguments to parse
+fn get_game(mut args: impl Iterator<Item = String>) -> Result<Game, String> {
+    let mut path = None;
+    let mut cpu = CpuOptions::default();
+    let mut palette = PaletteOptions::default();
+    while let Some(arg) = args.next() {
+        if cpu.parse_arg(&arg, &mut args)? || palette.parse_arg(&arg, &mut args)? {
+            continue;
+        }
+        match arg {
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
     }
-
-    match env::args().nth(1) {
-        Some(x) => match Path::new(&x) {
-            x if x.is_file() == true => Some(x.to_path_buf()),
-            _ => None,
-        },
-        _ => None,
+    let path = path.ok_or("no rom given")?;
+    if !path.is_file() {
+        return Err(format!("{} is not a file", path.display()));
     }
+    Ok(Game { path, cpu, palette })
 }